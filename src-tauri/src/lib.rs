@@ -8,8 +8,20 @@ use tauri_plugin_store::StoreExt;
 use uuid::Uuid;
 
 mod modules;
+use modules::album;
+use modules::bundle;
 use modules::config;
+use modules::dates;
+use modules::edits;
+use modules::export;
+use modules::gallery;
+use modules::hash;
+use modules::integrity;
+use modules::journal;
 use modules::library;
+use modules::referenced;
+use modules::search;
+use modules::tags;
 use modules::utils::{self, Item};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,12 +53,55 @@ pub fn run() {
             config::check_library_path,
             config::create_library,
             config::update_library_path,
+            config::set_library_write_sidecars,
             config::remove_library,
             config::get_selected_library,
             config::set_selected_library,
             library::get_items,
             library::add_items,
             library::set_items_favorite,
+            library::set_items_rating,
+            library::set_items_color_label,
+            library::set_items_flag,
+            library::set_item_details,
+            library::set_items_title,
+            library::set_items_caption,
+            library::set_items_notes,
+            library::get_item_xmp,
+            library::delete_items,
+            referenced::refresh_item_status,
+            referenced::consolidate_items,
+            dates::set_items_date,
+            dates::shift_items_date,
+            dates::set_items_time_zone,
+            dates::revert_items_date,
+            edits::get_item_edits,
+            edits::set_item_edits,
+            edits::revert_item_edits,
+            edits::rotate_items,
+            export::export_items,
+            gallery::export_album_gallery,
+            bundle::export_album_bundle,
+            bundle::import_album_bundle,
+            search::search,
+            album::create_smart_album,
+            album::update_smart_album_rule,
+            album::get_smart_album_items,
+            album::add_items_to_album,
+            album::remove_items_from_album,
+            tags::get_tags,
+            tags::create_tag,
+            tags::rename_tag,
+            tags::merge_tags,
+            tags::delete_tag,
+            tags::tag_items,
+            tags::untag_items,
+            journal::get_history,
+            journal::undo,
+            journal::redo,
+            integrity::verify_library,
+            integrity::repair_library,
+            hash::rehash_library,
             delete_photo,
             get_albums,
             create_album,
@@ -280,7 +335,7 @@ fn get_album_photos(
         .map_err(|e| e.to_string())?;
 
     let item_iter = stmt
-        .query_map(params![album_id], |row| utils::deserialize_item(row))
+        .query_map(params![album_id], utils::deserialize_item)
         .map_err(|e| e.to_string())?;
 
     let mut items = Vec::new();
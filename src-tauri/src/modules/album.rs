@@ -0,0 +1,401 @@
+use chrono::{DateTime, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, Row};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::modules::journal;
+use crate::modules::library;
+use crate::modules::tags;
+use crate::modules::utils;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Album {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub parent: Option<String>,
+    pub color: Option<String>,
+    pub emoji: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub smart_rule: Option<SmartRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartRule {
+    #[serde(default = "match_all_default")]
+    pub match_all: bool,
+    pub conditions: Vec<RuleCondition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleCondition {
+    DateRange { from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>> },
+    Favorite { value: bool },
+    FileType { value: String },
+    Camera { value: String },
+    Dimensions { min_width: Option<u32>, max_width: Option<u32>, min_height: Option<u32>, max_height: Option<u32> },
+    Album { album_id: String, member: bool },
+    Keyword { value: String },
+    Color { value: String },
+}
+
+fn match_all_default() -> bool {
+    true
+}
+
+impl SmartRule {
+    pub fn to_sql(&self) -> (String, Vec<Value>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        for condition in &self.conditions {
+            conditions.push(condition.to_sql(&mut values));
+        }
+
+        if conditions.is_empty() {
+            return ("1".to_string(), values);
+        }
+
+        let separator = if self.match_all { " AND " } else { " OR " };
+        (format!("({})", conditions.join(separator)), values)
+    }
+}
+
+impl RuleCondition {
+    fn to_sql(&self, values: &mut Vec<Value>) -> String {
+        match self {
+            RuleCondition::DateRange { from, to } => {
+                let mut parts = Vec::new();
+                if let Some(from) = from {
+                    parts.push("item.created_at >= ?");
+                    values.push(Value::Text(from.to_rfc3339()));
+                }
+                if let Some(to) = to {
+                    parts.push("item.created_at <= ?");
+                    values.push(Value::Text(to.to_rfc3339()));
+                }
+                join_all(parts)
+            }
+            RuleCondition::Favorite { value } => {
+                values.push(Value::Integer(*value as i64));
+                "item.is_favorite = ?".to_string()
+            }
+            RuleCondition::FileType { value } => {
+                if value.ends_with('/') {
+                    values.push(Value::Text(format!("{}%", value)));
+                    "item.file_type LIKE ?".to_string()
+                } else {
+                    values.push(Value::Text(value.clone()));
+                    "item.file_type = ?".to_string()
+                }
+            }
+            RuleCondition::Camera { value } => {
+                values.push(Value::Text(value.clone()));
+                "item.camera_model = ? COLLATE NOCASE".to_string()
+            }
+            RuleCondition::Dimensions { min_width, max_width, min_height, max_height } => {
+                let mut parts = Vec::new();
+                for (bound, sql) in [
+                    (min_width, "item.width >= ?"),
+                    (max_width, "item.width <= ?"),
+                    (min_height, "item.height >= ?"),
+                    (max_height, "item.height <= ?"),
+                ] {
+                    if let Some(bound) = bound {
+                        parts.push(sql);
+                        values.push(Value::Integer(*bound as i64));
+                    }
+                }
+                join_all(parts)
+            }
+            RuleCondition::Album { album_id, member } => {
+                values.push(Value::Text(album_id.clone()));
+                format!(
+                    "{}EXISTS (SELECT 1 FROM album_item WHERE album_item.item_id = item.id AND album_item.album_id = ?)",
+                    if *member { "" } else { "NOT " }
+                )
+            }
+            RuleCondition::Keyword { value } => {
+                values.extend(std::iter::repeat_n(Value::Text(value.clone()), 3));
+                format!(
+                    "EXISTS (SELECT 1 FROM item_tag JOIN tag ON tag.id = item_tag.tag_id WHERE item_tag.item_id = item.id AND {})",
+                    tags::path_condition("tag.path", "?")
+                )
+            }
+            RuleCondition::Color { value } => {
+                values.push(Value::Text(value.clone()));
+                "item.color_label = ? COLLATE NOCASE".to_string()
+            }
+        }
+    }
+}
+
+fn join_all(parts: Vec<&str>) -> String {
+    if parts.is_empty() {
+        "1".to_string()
+    } else {
+        format!("({})", parts.join(" AND "))
+    }
+}
+
+pub fn deserialize_album(row: &Row<'_>) -> Result<Album, rusqlite::Error> {
+    Ok(Album {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        description: row.get("description")?,
+        parent: row.get("parent")?,
+        color: row.get("color")?,
+        emoji: row.get("emoji")?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>("created_at")?)
+            .map_err(|_| {
+                rusqlite::Error::InvalidColumnType(6, "created_at".to_string(), rusqlite::types::Type::Text)
+            })?.with_timezone(&Utc),
+        smart_rule: row.get::<_, Option<String>>("smart_rule")?.and_then(|rule| serde_json::from_str(&rule).ok()),
+    })
+}
+
+fn is_smart_album(conn: &Connection, album_id: &str) -> Result<bool, String> {
+    conn.query_row("SELECT smart_rule IS NOT NULL FROM album WHERE id = ?1", params![album_id], |row| row.get(0))
+        .map_err(|e| utils::treat(e, "Album not found"))
+}
+
+fn validate_rule(conn: &Connection, rule: &SmartRule) -> Result<(), String> {
+    for condition in &rule.conditions {
+        if let RuleCondition::Album { album_id, .. } = condition {
+            if is_smart_album(conn, album_id)? {
+                return Err(utils::treat_msg("A smart album cannot be used in an album condition"));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn create_smart_album(app: tauri::AppHandle, library_id: String, name: String, description: Option<String>, rule: SmartRule) -> Result<Album, String> {
+    let conn = library::get_db_connection(&app, &library_id)?;
+    validate_rule(&conn, &rule)?;
+    let album = Album {
+        id: Uuid::new_v4().to_string(),
+        name,
+        description,
+        parent: None,
+        color: None,
+        emoji: None,
+        created_at: Utc::now(),
+        smart_rule: Some(rule),
+    };
+    let rule_json = serde_json::to_string(&album.smart_rule).map_err(|e| utils::treat(e, "Unable to save the smart album rules"))?;
+
+    conn.execute(
+        "INSERT INTO album (id, name, description, created_at, smart_rule) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![album.id, album.name, album.description, album.created_at.to_rfc3339(), rule_json],
+    ).map_err(|e| utils::treat(e, "Unable to create the smart album"))?;
+
+    Ok(album)
+}
+
+#[tauri::command]
+pub fn update_smart_album_rule(app: tauri::AppHandle, library_id: String, album_id: String, rule: SmartRule) -> Result<(), String> {
+    let conn = library::get_db_connection(&app, &library_id)?;
+    validate_rule(&conn, &rule)?;
+    let rule_json = serde_json::to_string(&rule).map_err(|e| utils::treat(e, "Unable to save the smart album rules"))?;
+
+    let updated = conn.execute(
+        "UPDATE album SET smart_rule = ?1 WHERE id = ?2",
+        params![rule_json, album_id],
+    ).map_err(|e| utils::treat(e, "Unable to save the smart album rules"))?;
+
+    if updated == 0 {
+        return Err(utils::treat_msg("Album not found"));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn add_items_to_album(app: tauri::AppHandle, library_id: String, album_id: String, item_ids: Vec<String>) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    if is_smart_album(&conn, &album_id)? {
+        return Err(utils::treat_msg("Items cannot be added to a smart album"));
+    }
+
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "album_item", "item_id", &item_ids)?;
+    let now = Utc::now().to_rfc3339();
+    for item_id in &item_ids {
+        tx.execute(
+            "INSERT OR IGNORE INTO album_item (album_id, item_id, added_at) VALUES (?1, ?2, ?3)",
+            params![album_id, item_id, now],
+        ).map_err(|e| utils::treat(e, "Unable to add the item to the album"))?;
+    }
+    journal::record(&tx, "add_items_to_album", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save the album"))
+}
+
+#[tauri::command]
+pub fn remove_items_from_album(app: tauri::AppHandle, library_id: String, album_id: String, item_ids: Vec<String>) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "album_item", "item_id", &item_ids)?;
+    for item_id in &item_ids {
+        tx.execute(
+            "DELETE FROM album_item WHERE album_id = ?1 AND item_id = ?2",
+            params![album_id, item_id],
+        ).map_err(|e| utils::treat(e, "Unable to remove the item from the album"))?;
+    }
+    journal::record(&tx, "remove_items_from_album", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save the album"))
+}
+
+#[tauri::command]
+pub fn get_smart_album_items(app: tauri::AppHandle, library_id: String, album_id: String) -> Result<Vec<utils::Item>, String> {
+    let conn = library::get_db_connection(&app, &library_id)?;
+    let album = conn
+        .query_row("SELECT * FROM album WHERE id = ?1", params![album_id], deserialize_album)
+        .map_err(|e| utils::treat(e, "Album not found"))?;
+    let rule = album.smart_rule.ok_or_else(|| utils::treat_msg("This album is not a smart album"))?;
+
+    let (condition, values) = rule.to_sql();
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM item WHERE {} ORDER BY created_at DESC", condition))
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+    let item_iter = stmt.query_map(params_from_iter(values), utils::deserialize_item).map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+
+    let mut items = Vec::new();
+    for item in item_iter {
+        items.push(item.map_err(|e| utils::treat(e, "Unable to obtain items"))?);
+    }
+
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(match_all: bool, conditions: Vec<RuleCondition>) -> SmartRule {
+        SmartRule { match_all, conditions }
+    }
+
+    fn library() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE item (id TEXT PRIMARY KEY, created_at TEXT, is_favorite INTEGER, file_type TEXT, camera_model TEXT, width INTEGER, height INTEGER, color_label TEXT);
+            CREATE TABLE album (id TEXT PRIMARY KEY, smart_rule TEXT);
+            CREATE TABLE album_item (album_id TEXT, item_id TEXT);
+            CREATE TABLE tag (id TEXT PRIMARY KEY, path TEXT NOT NULL);
+            CREATE TABLE item_tag (item_id TEXT NOT NULL, tag_id TEXT NOT NULL);
+            INSERT INTO tag VALUES ('japan', 'Travel/Japan'), ('travelling', 'Travelling'), ('wildcard', 'Tr_vel');
+            INSERT INTO item_tag VALUES ('a', 'japan'), ('b', 'travelling'), ('c', 'wildcard');
+            INSERT INTO item VALUES ('a', '2024-03-01T10:00:00+00:00', 1, 'image/jpeg', 'X100V', 6240, 4160, 'Red');
+            INSERT INTO item VALUES ('b', '2023-07-14T10:00:00+00:00', 1, 'image/heic', 'iPhone 15', 4032, 3024, NULL);
+            INSERT INTO item VALUES ('c', '2024-11-20T10:00:00+00:00', 0, 'image/jpeg', 'x100v', 1920, 1080, 'red');
+            INSERT INTO album VALUES ('trip', NULL), ('smart', '{\"conditions\":[]}');
+            INSERT INTO album_item VALUES ('trip', 'b'), ('trip', 'c');",
+        ).unwrap();
+        conn
+    }
+
+    fn matching(conn: &Connection, rule: &SmartRule) -> Vec<String> {
+        let (condition, values) = rule.to_sql();
+        let mut stmt = conn.prepare(&format!("SELECT id FROM item WHERE {} ORDER BY id", condition)).unwrap();
+        stmt.query_map(params_from_iter(values), |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap()
+    }
+
+    fn date(value: &str) -> Option<DateTime<Utc>> {
+        Some(DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc))
+    }
+
+    #[test]
+    fn empty_rule_matches_everything() {
+        let (sql, values) = rule(true, Vec::new()).to_sql();
+        assert_eq!(sql, "1");
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn conditions_are_joined_by_match_mode() {
+        let conditions = vec![RuleCondition::Favorite { value: true }, RuleCondition::Camera { value: "X100V".to_string() }];
+
+        let (all, values) = rule(true, conditions.clone()).to_sql();
+        assert_eq!(all, "(item.is_favorite = ? AND item.camera_model = ? COLLATE NOCASE)");
+        assert_eq!(values, vec![Value::Integer(1), Value::Text("X100V".to_string())]);
+
+        let (any, _) = rule(false, conditions).to_sql();
+        assert_eq!(any, "(item.is_favorite = ? OR item.camera_model = ? COLLATE NOCASE)");
+    }
+
+    #[test]
+    fn open_bounds_only_bind_given_values() {
+        let mut values = Vec::new();
+        let sql = RuleCondition::DateRange { from: date("2024-01-01T00:00:00+00:00"), to: None }.to_sql(&mut values);
+        assert_eq!(sql, "(item.created_at >= ?)");
+        assert_eq!(values.len(), 1);
+
+        let mut values = Vec::new();
+        let sql = RuleCondition::Dimensions { min_width: None, max_width: None, min_height: None, max_height: None }.to_sql(&mut values);
+        assert_eq!(sql, "1");
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn rules_select_matching_items() {
+        let conn = library();
+        let favorites_2024 = rule(true, vec![
+            RuleCondition::DateRange { from: date("2024-01-01T00:00:00+00:00"), to: date("2024-12-31T23:59:59+00:00") },
+            RuleCondition::Favorite { value: true },
+        ]);
+        assert_eq!(matching(&conn, &favorites_2024), ["a"]);
+
+        let x100v_or_heic = rule(false, vec![
+            RuleCondition::Camera { value: "X100V".to_string() },
+            RuleCondition::FileType { value: "image/heic".to_string() },
+        ]);
+        assert_eq!(matching(&conn, &x100v_or_heic), ["a", "b", "c"]);
+
+        let large_jpegs = rule(true, vec![
+            RuleCondition::FileType { value: "image/".to_string() },
+            RuleCondition::Dimensions { min_width: Some(4000), max_width: None, min_height: None, max_height: None },
+            RuleCondition::Album { album_id: "trip".to_string(), member: false },
+        ]);
+        assert_eq!(matching(&conn, &large_jpegs), ["a"]);
+
+        let red = rule(true, vec![RuleCondition::Color { value: "RED".to_string() }]);
+        assert_eq!(matching(&conn, &red), ["a", "c"]);
+    }
+
+    #[test]
+    fn keyword_rules_match_tag_hierarchies() {
+        let conn = library();
+        let keyword = |value: &str| rule(true, vec![RuleCondition::Keyword { value: value.to_string() }]);
+
+        assert_eq!(matching(&conn, &keyword("Travel")), ["a"]);
+        assert_eq!(matching(&conn, &keyword("Travel/Japan")), ["a"]);
+        assert!(matching(&conn, &keyword("travel")).is_empty());
+        assert_eq!(matching(&conn, &keyword("Tr_vel")), ["c"]);
+        assert!(matching(&conn, &keyword("Trav%")).is_empty());
+    }
+
+    #[test]
+    fn smart_albums_cannot_be_album_conditions() {
+        let conn = library();
+        let regular = rule(true, vec![RuleCondition::Album { album_id: "trip".to_string(), member: true }]);
+        let smart = rule(true, vec![RuleCondition::Album { album_id: "smart".to_string(), member: true }]);
+
+        assert!(validate_rule(&conn, &regular).is_ok());
+        assert!(validate_rule(&conn, &smart).is_err());
+        assert!(is_smart_album(&conn, "smart").unwrap());
+        assert!(!is_smart_album(&conn, "trip").unwrap());
+    }
+}
\ No newline at end of file
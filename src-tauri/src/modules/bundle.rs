@@ -0,0 +1,415 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io;
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::modules::album::{self, Album, RuleCondition};
+use crate::modules::edits;
+use crate::modules::hash;
+use crate::modules::library::{self, ImportMode, PreparedItem};
+use crate::modules::sidecar;
+use crate::modules::staging::ImportStage;
+use crate::modules::tags;
+use crate::modules::utils;
+
+const MANIFEST_NAME: &str = "manifest.json";
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleManifest {
+    version: u32,
+    albums: Vec<BundleAlbum>,
+    items: Vec<BundleItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleAlbum {
+    album: Album,
+    item_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleItem {
+    item: utils::Item,
+    tags: Vec<String>,
+    file: String,
+}
+
+struct BundleMerge {
+    item_id: String,
+    tags: Vec<String>,
+    rating: u8,
+}
+
+struct StagedBundle {
+    prepared: Vec<PreparedItem>,
+    item_map: HashMap<String, String>,
+    merges: Vec<BundleMerge>,
+}
+
+#[tauri::command]
+pub async fn export_album_bundle(app: tauri::AppHandle, library_id: String, album_id: String, destination: String) -> Result<String, String> {
+    let library_root = library::get_library_root_path(&app, &library_id)?;
+    let conn = library::get_db_connection(&app, &library_id)?;
+
+    let mut stmt = conn
+        .prepare(
+            "WITH RECURSIVE tree (id) AS (
+                SELECT id FROM album WHERE id = ?1
+                UNION ALL
+                SELECT album.id FROM album JOIN tree ON album.parent = tree.id
+            )
+            SELECT album.* FROM album JOIN tree ON tree.id = album.id",
+        )
+        .map_err(|e| utils::treat(e, "Unable to obtain albums"))?;
+    let album_iter = stmt.query_map(params![album_id], album::deserialize_album).map_err(|e| utils::treat(e, "Unable to obtain albums"))?;
+
+    let mut albums = Vec::new();
+    for album in album_iter {
+        let album = album.map_err(|e| utils::treat(e, "Unable to obtain albums"))?;
+        let mut stmt = conn
+            .prepare("SELECT item_id FROM album_item WHERE album_id = ?1 ORDER BY added_at")
+            .map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+        let item_ids = stmt
+            .query_map(params![album.id], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<Result<Vec<String>, _>>())
+            .map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+
+        albums.push(BundleAlbum { album, item_ids });
+    }
+
+    if albums.is_empty() {
+        return Err(utils::treat_msg("Album not found"));
+    }
+
+    let mut seen = HashSet::new();
+    let mut items = Vec::new();
+    for item_id in albums.iter().flat_map(|album| &album.item_ids) {
+        if !seen.insert(item_id.clone()) {
+            continue;
+        }
+
+        let item = conn
+            .query_row("SELECT * FROM item WHERE id = ?1", params![item_id], utils::deserialize_item)
+            .map_err(|e| utils::treat(e, "Item not found"))?;
+        let tags = tags::get_item_tag_paths(&conn, item_id)?;
+        let file = format!("originals/{}", utils::original_file_name(&item));
+
+        items.push(BundleItem { item, tags, file });
+    }
+
+    let file = File::create(&destination).map_err(|e| utils::treat(e, "Unable to create the bundle"))?;
+    let mut writer = ZipWriter::new(file);
+
+    for bundle_item in &items {
+        let mut original = File::open(utils::original_path(&library_root, &bundle_item.item)).map_err(|e| utils::treat(e, "Unable to read the original file"))?;
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .large_file(bundle_item.item.file_size > u32::MAX as u64);
+
+        writer.start_file(bundle_item.file.as_str(), options).map_err(|e| utils::treat(e, "Unable to write the bundle"))?;
+        io::copy(&mut original, &mut writer).map_err(|e| utils::treat(e, "Unable to write the bundle"))?;
+    }
+
+    let manifest = BundleManifest { version: BUNDLE_VERSION, albums, items };
+    writer
+        .start_file(MANIFEST_NAME, SimpleFileOptions::default().compression_method(CompressionMethod::Deflated))
+        .map_err(|e| utils::treat(e, "Unable to write the bundle"))?;
+    serde_json::to_writer_pretty(&mut writer, &manifest).map_err(|e| utils::treat(e, "Unable to write the bundle"))?;
+    writer.finish().map_err(|e| utils::treat(e, "Unable to write the bundle"))?;
+
+    Ok(destination)
+}
+
+#[tauri::command]
+pub async fn import_album_bundle(app: tauri::AppHandle, library_id: String, bundle_path: String) -> Result<Vec<Album>, String> {
+    let file = File::open(&bundle_path).map_err(|e| utils::treat(e, "Unable to open the bundle"))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| utils::treat(e, "Invalid bundle file"))?;
+    let manifest: BundleManifest = {
+        let entry = archive.by_name(MANIFEST_NAME).map_err(|e| utils::treat(e, "Invalid bundle file"))?;
+        serde_json::from_reader(entry).map_err(|e| utils::treat(e, "Invalid bundle file"))?
+    };
+
+    validate_manifest(&manifest)?;
+
+    let library_root = library::get_library_root_path(&app, &library_id)?;
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let stage = ImportStage::begin(&conn, &library_root)?;
+    let staged = match stage_items(&conn, manifest.items, &mut archive, &stage) {
+        Ok(staged) => staged,
+        Err(e) => {
+            stage.abort(&conn)?;
+            return Err(e);
+        }
+    };
+
+    let (albums, _) = stage.commit(&mut conn, |tx| {
+        library::insert_items(tx, &staged.prepared)?;
+        merge_items(tx, &staged.merges)?;
+        insert_albums(tx, manifest.albums, &staged.item_map)
+    })?;
+
+    let mut item_ids: Vec<String> = staged.prepared.into_iter().map(|prepared| prepared.item.id).chain(staged.merges.into_iter().map(|merge| merge.item_id)).collect();
+    item_ids.sort();
+    item_ids.dedup();
+    if let Err(e) = sidecar::sync_items(&app, &library_id, &item_ids) {
+        log::warn!("Unable to write the sidecars of imported items: {}", e);
+    }
+    Ok(albums)
+}
+
+fn validate_manifest(manifest: &BundleManifest) -> Result<(), String> {
+    if manifest.version > BUNDLE_VERSION {
+        return Err(utils::treat_msg("This bundle was created by a newer version of the app"));
+    }
+
+    let mut item_ids = HashSet::new();
+    for BundleItem { item, file, .. } in &manifest.items {
+        if !item_ids.insert(item.id.as_str()) || file.is_empty() {
+            return Err(utils::treat_msg("Invalid bundle file"));
+        }
+    }
+
+    let mut album_ids = HashSet::new();
+    for (index, BundleAlbum { album, item_ids: members }) in manifest.albums.iter().enumerate() {
+        let parent_known = index == 0 || album.parent.as_ref().is_none_or(|parent| album_ids.contains(parent.as_str()));
+        if !parent_known || !album_ids.insert(album.id.as_str()) || !members.iter().all(|member| item_ids.contains(member.as_str())) {
+            return Err(utils::treat_msg("Invalid bundle file"));
+        }
+    }
+
+    let rule_albums = manifest.albums.iter().filter_map(|bundle_album| bundle_album.album.smart_rule.as_ref()).flat_map(|rule| &rule.conditions);
+    for condition in rule_albums {
+        if let RuleCondition::Album { album_id, .. } = condition {
+            if !album_ids.contains(album_id.as_str()) {
+                return Err(utils::treat_msg("The bundle has smart albums that refer to albums outside the bundle"));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn stage_items(
+    conn: &Connection,
+    items: Vec<BundleItem>,
+    archive: &mut ZipArchive<File>,
+    stage: &ImportStage,
+) -> Result<StagedBundle, String> {
+    let extract_dir = stage.dir("bundle");
+    fs::create_dir_all(&extract_dir).map_err(|e| utils::treat(e, "Unable to create required directory"))?;
+    let mut item_map = HashMap::new();
+    let mut checksum_map: HashMap<String, String> = HashMap::new();
+    let mut prepared = Vec::new();
+    let mut merges = Vec::new();
+
+    for BundleItem { item: source, tags, file } in items {
+        let staged_path = extract_dir.join(format!("{}.{}", Uuid::new_v4(), utils::file_extension(&source.original_name)));
+        {
+            let mut entry = archive.by_name(&file).map_err(|e| utils::treat(e, "The bundle is missing an original file"))?;
+            let mut staged = File::create(&staged_path).map_err(|e| utils::treat(e, "Unable to extract the bundle"))?;
+            io::copy(&mut entry, &mut staged).map_err(|e| utils::treat(e, "Unable to extract the bundle"))?;
+        }
+
+        let checksums = hash::hash_file_with(&staged_path, &[source.checksum_algorithm, hash::CURRENT_ALGORITHM])?;
+        if checksums[0] != source.checksum {
+            return Err(utils::treat_msg("The bundle is corrupted"));
+        }
+
+        let existing = match checksum_map.get(&checksums[1]) {
+            Some(existing) => Some(existing.clone()),
+            None => hash::find_duplicate(conn, &staged_path)?,
+        };
+        if let Some(existing) = existing {
+            fs::remove_file(&staged_path).map_err(|e| utils::treat(e, "Unable to extract the bundle"))?;
+            item_map.insert(source.id, existing.clone());
+            merges.push(BundleMerge { item_id: existing, tags, rating: source.rating });
+            continue;
+        }
+
+        let PreparedItem { mut item, .. } = library::prepare_item(&staged_path.to_string_lossy(), stage, ImportMode::Move)?;
+
+        item.original_name = source.original_name;
+        item.created_at = source.created_at;
+        item.time_zone = source.time_zone;
+        item.original_created_at = source.original_created_at;
+        item.original_time_zone = source.original_time_zone;
+        item.is_favorite = source.is_favorite;
+        item.is_screenshot = source.is_screenshot;
+        item.is_screen_recording = source.is_screen_recording;
+        item.camera_model = source.camera_model;
+        item.place_name = source.place_name;
+        item.rating = source.rating;
+        item.color_label = source.color_label;
+        item.flag = source.flag;
+        item.title = source.title;
+        item.caption = source.caption;
+        item.notes = source.notes;
+        item.latitude = source.latitude;
+        item.longitude = source.longitude;
+        item.crop = source.crop;
+        item.edits = source.edits;
+        if !item.edits.is_empty() {
+            edits::render_previews(stage.root(), &item)?;
+        }
+
+        item_map.insert(source.id, item.id.clone());
+        checksum_map.insert(item.checksum.clone(), item.id.clone());
+        prepared.push(PreparedItem { item, keywords: tags });
+    }
+
+    Ok(StagedBundle { prepared, item_map, merges })
+}
+
+fn merge_items(tx: &Transaction, merges: &[BundleMerge]) -> Result<(), String> {
+    for merge in merges {
+        tx.execute("UPDATE item SET rating = MAX(rating, ?2) WHERE id = ?1", params![merge.item_id, merge.rating])
+            .map_err(|e| utils::treat(e, "Unable to update the item"))?;
+
+        for tag in &merge.tags {
+            let tag_id = tags::ensure_tag_path(tx, tag)?;
+            tx.execute(
+                "INSERT OR IGNORE INTO item_tag (item_id, tag_id, added_at) VALUES (?1, ?2, ?3)",
+                params![merge.item_id, tag_id, Utc::now().to_rfc3339()],
+            ).map_err(|e| utils::treat(e, "Unable to tag the item"))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn insert_albums(tx: &Transaction, bundle_albums: Vec<BundleAlbum>, item_map: &HashMap<String, String>) -> Result<Vec<Album>, String> {
+    let album_map: HashMap<String, String> = bundle_albums.iter().map(|bundle_album| (bundle_album.album.id.clone(), Uuid::new_v4().to_string())).collect();
+    let mut albums = Vec::new();
+    for BundleAlbum { mut album, item_ids } in bundle_albums {
+        album.id = album_map[&album.id].clone();
+        album.parent = album.parent.and_then(|parent| album_map.get(&parent).cloned());
+        for condition in album.smart_rule.iter_mut().flat_map(|rule| &mut rule.conditions) {
+            if let RuleCondition::Album { album_id, .. } = condition {
+                *album_id = album_map.get(album_id).cloned().ok_or_else(|| utils::treat_msg("Invalid bundle file"))?;
+            }
+        }
+        let rule_json = match &album.smart_rule {
+            Some(rule) => Some(serde_json::to_string(rule).map_err(|e| utils::treat(e, "Unable to save the smart album rules"))?),
+            None => None,
+        };
+
+        tx.execute(
+            "INSERT INTO album (id, name, description, parent, color, emoji, created_at, smart_rule) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![album.id, album.name, album.description, album.parent, album.color, album.emoji, album.created_at.to_rfc3339(), rule_json],
+        ).map_err(|e| utils::treat(e, "Unable to create the album"))?;
+
+        for item_id in item_ids.iter().filter_map(|item_id| item_map.get(item_id)) {
+            tx.execute(
+                "INSERT OR IGNORE INTO album_item (album_id, item_id, added_at) VALUES (?1, ?2, ?3)",
+                params![album.id, item_id, Utc::now().to_rfc3339()],
+            ).map_err(|e| utils::treat(e, "Unable to add items to the album"))?;
+        }
+
+        albums.push(album);
+    }
+
+    Ok(albums)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::album::SmartRule;
+    use crate::modules::schema;
+
+    fn manifest(items: &[&str], albums: &[(&str, Option<&str>, &[&str])]) -> BundleManifest {
+        let items: Vec<_> = items
+            .iter()
+            .map(|id| {
+                serde_json::json!({
+                    "item": {
+                        "id": id, "original_name": "photo.jpg", "file_type": "image/jpeg", "file_size": 1, "width": 1, "height": 1,
+                        "checksum": "", "is_favorite": false, "is_screenshot": false, "is_screen_recording": false,
+                        "created_at": "2024-01-01T00:00:00Z", "page_count": 1, "is_animated": false, "frame_count": 1,
+                        "is_hdr": false, "has_gain_map": false, "bit_depth": 8, "rating": 0, "flag": 0, "edits": []
+                    },
+                    "tags": [],
+                    "file": format!("originals/{}.jpg", id)
+                })
+            })
+            .collect();
+        let albums: Vec<_> = albums
+            .iter()
+            .map(|(id, parent, item_ids)| {
+                serde_json::json!({
+                    "album": { "id": id, "name": id, "parent": parent, "created_at": "2024-01-01T00:00:00Z" },
+                    "item_ids": item_ids
+                })
+            })
+            .collect();
+
+        serde_json::from_value(serde_json::json!({ "version": BUNDLE_VERSION, "albums": albums, "items": items })).unwrap()
+    }
+
+    #[test]
+    fn valid_manifests_are_accepted() {
+        let manifest = manifest(&["a", "b"], &[("root", Some("outside"), &["a"]), ("child", Some("root"), &["a", "b"])]);
+        assert!(validate_manifest(&manifest).is_ok());
+    }
+
+    #[test]
+    fn inconsistent_manifests_are_rejected() {
+        let mut newer = manifest(&["a"], &[]);
+        newer.version = BUNDLE_VERSION + 1;
+
+        assert!(validate_manifest(&newer).is_err());
+        assert!(validate_manifest(&manifest(&["a", "a"], &[])).is_err());
+        assert!(validate_manifest(&manifest(&["a"], &[("root", None, &["missing"])])).is_err());
+        assert!(validate_manifest(&manifest(&["a"], &[("root", None, &[]), ("child", Some("unknown"), &[])])).is_err());
+        assert!(validate_manifest(&manifest(&["a"], &[("root", None, &[]), ("root", None, &[])])).is_err());
+    }
+
+    #[test]
+    fn smart_album_conditions_follow_imported_albums() {
+        let mut bundle = manifest(&["a"], &[("smart", None, &[]), ("source", None, &["a"])]);
+        bundle.albums[0].album.smart_rule = Some(SmartRule { match_all: true, conditions: vec![RuleCondition::Album { album_id: "source".to_string(), member: true }] });
+        assert!(validate_manifest(&bundle).is_ok());
+
+        let mut conn = schema::base_library();
+        schema::migrate(&conn).unwrap();
+        let tx = conn.transaction().unwrap();
+        let albums = insert_albums(&tx, std::mem::take(&mut bundle.albums), &HashMap::new()).unwrap();
+        match &albums[0].smart_rule.as_ref().unwrap().conditions[0] {
+            RuleCondition::Album { album_id, .. } => assert_eq!(album_id, &albums[1].id),
+            _ => panic!("unexpected condition"),
+        }
+
+        let mut outside = manifest(&["a"], &[("smart", None, &[])]);
+        outside.albums[0].album.smart_rule = Some(SmartRule { match_all: true, conditions: vec![RuleCondition::Album { album_id: "outside".to_string(), member: true }] });
+        assert!(validate_manifest(&outside).is_err());
+    }
+
+    #[test]
+    fn duplicates_receive_bundle_tags_and_ratings() {
+        let mut conn = schema::base_library();
+        schema::migrate(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO item (id, original_name, file_type, file_size, width, height, checksum, created_at, rating) VALUES ('a', 'a.jpg', 'image/jpeg', 1, 1, 1, 'x', '2024-01-01T00:00:00Z', 2)",
+            [],
+        )
+        .unwrap();
+
+        let tx = conn.transaction().unwrap();
+        let merges = [
+            BundleMerge { item_id: "a".to_string(), tags: vec!["Travel/Japan".to_string()], rating: 4 },
+            BundleMerge { item_id: "a".to_string(), tags: vec!["Travel/Japan".to_string()], rating: 1 },
+        ];
+        merge_items(&tx, &merges).unwrap();
+        tx.commit().unwrap();
+
+        let rating: u8 = conn.query_row("SELECT rating FROM item WHERE id = 'a'", [], |row| row.get(0)).unwrap();
+        assert_eq!(rating, 4);
+        assert_eq!(tags::get_item_tag_paths(&conn, "a").unwrap(), vec!["Travel/Japan"]);
+    }
+}
\ No newline at end of file
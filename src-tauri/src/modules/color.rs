@@ -0,0 +1,143 @@
+use image::{DynamicImage, Rgb, RgbImage};
+use lcms2::{InfoType, Intent, Locale, PixelFormat, Profile, Transform};
+
+use crate::modules::utils;
+
+pub const GAIN_MAP_MARKERS: &[&[u8]] = &[b"http://ns.adobe.com/hdr-gain-map/1.0/", b"urn:iso:std:iso:ts:21496:-1"];
+const REFERENCE_WHITE_NITS: f32 = 203.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HdrTransfer {
+    Pq,
+    Hlg,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HdrInfo {
+    pub transfer: Option<HdrTransfer>,
+    pub has_gain_map: bool,
+    pub bit_depth: u8,
+}
+
+impl HdrInfo {
+    pub fn sdr(bit_depth: u8) -> Self {
+        HdrInfo { transfer: None, has_gain_map: false, bit_depth }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.transfer.is_some() || self.has_gain_map
+    }
+}
+
+pub fn has_jpeg_gain_map(data: &[u8]) -> bool {
+    GAIN_MAP_MARKERS.iter().any(|marker| data.windows(marker.len()).any(|window| window == *marker))
+}
+
+pub fn bit_depth(image: &DynamicImage) -> u8 {
+    let color = image.color();
+    (color.bits_per_pixel() / color.channel_count() as u16) as u8
+}
+
+pub fn prepare_for_display(image: DynamicImage, icc_profile: Option<&[u8]>, transfer: Option<HdrTransfer>) -> DynamicImage {
+    let image = match transfer {
+        Some(transfer) => tone_map(&image, transfer),
+        None => to_srgb(image, icc_profile),
+    };
+
+    if image.color().has_alpha() {
+        DynamicImage::ImageRgba8(image.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    }
+}
+
+pub fn color_space_name(icc_profile: &[u8]) -> Option<String> {
+    let profile = Profile::new_icc(icc_profile).ok()?;
+    profile.info(InfoType::Description, Locale::none()).map(|name| name.trim().to_string()).filter(|name| !name.is_empty())
+}
+
+pub fn to_srgb(image: DynamicImage, icc_profile: Option<&[u8]>) -> DynamicImage {
+    let Some(icc_profile) = icc_profile else {
+        return image;
+    };
+
+    convert_to_srgb(&image, icc_profile).unwrap_or(image)
+}
+
+fn convert_to_srgb(image: &DynamicImage, icc_profile: &[u8]) -> Result<DynamicImage, String> {
+    let source = Profile::new_icc(icc_profile).map_err(|e| utils::treat(e, "Unable to read the color profile"))?;
+    let srgb = Profile::new_srgb();
+
+    if image.color().has_alpha() {
+        let transform = Transform::<u8, u8>::new(&source, PixelFormat::RGBA_8, &srgb, PixelFormat::RGBA_8, Intent::Perceptual)
+            .map_err(|e| utils::treat(e, "Unable to convert the color profile"))?;
+        let mut pixels = image.to_rgba8();
+        transform.transform_in_place(&mut pixels);
+        Ok(DynamicImage::ImageRgba8(pixels))
+    } else {
+        let transform = Transform::<u8, u8>::new(&source, PixelFormat::RGB_8, &srgb, PixelFormat::RGB_8, Intent::Perceptual)
+            .map_err(|e| utils::treat(e, "Unable to convert the color profile"))?;
+        let mut pixels = image.to_rgb8();
+        transform.transform_in_place(&mut pixels);
+        Ok(DynamicImage::ImageRgb8(pixels))
+    }
+}
+
+fn tone_map(image: &DynamicImage, transfer: HdrTransfer) -> DynamicImage {
+    let source = image.to_rgb32f();
+    let mut output = RgbImage::new(source.width(), source.height());
+
+    for (pixel, target) in source.pixels().zip(output.pixels_mut()) {
+        let linear = pixel.0.map(|value| match transfer {
+            HdrTransfer::Pq => pq_to_nits(value) / REFERENCE_WHITE_NITS,
+            HdrTransfer::Hlg => hlg_to_scene_linear(value) / hlg_to_scene_linear(0.75),
+        });
+        let [r, g, b] = bt2020_to_bt709(linear);
+        *target = Rgb([r, g, b].map(|value| encode_srgb(value / (1.0 + value))));
+    }
+
+    DynamicImage::ImageRgb8(output)
+}
+
+fn pq_to_nits(value: f32) -> f32 {
+    const M1: f32 = 2610.0 / 16384.0;
+    const M2: f32 = 2523.0 / 32.0;
+    const C1: f32 = 3424.0 / 4096.0;
+    const C2: f32 = 2413.0 / 128.0;
+    const C3: f32 = 2392.0 / 128.0;
+
+    let power = value.clamp(0.0, 1.0).powf(1.0 / M2);
+    10000.0 * ((power - C1).max(0.0) / (C2 - C3 * power)).powf(1.0 / M1)
+}
+
+fn hlg_to_scene_linear(value: f32) -> f32 {
+    const A: f32 = 0.17883277;
+    const B: f32 = 0.28466892;
+    const C: f32 = 0.559_910_7;
+
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.5 {
+        value * value / 3.0
+    } else {
+        (((value - C) / A).exp() + B) / 12.0
+    }
+}
+
+fn bt2020_to_bt709([r, g, b]: [f32; 3]) -> [f32; 3] {
+    [
+        (1.6605 * r - 0.5876 * g - 0.0728 * b).max(0.0),
+        (-0.1246 * r + 1.1329 * g - 0.0083 * b).max(0.0),
+        (-0.0182 * r - 0.1006 * g + 1.1187 * b).max(0.0),
+    ]
+}
+
+fn encode_srgb(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let encoded = if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+
+    (encoded * 255.0).round() as u8
+}
\ No newline at end of file
@@ -8,6 +8,8 @@ use tauri::Wry;
 use tauri_plugin_store::{Store, StoreExt};
 use uuid::Uuid;
 
+use crate::modules::library;
+use crate::modules::schema;
 use crate::modules::utils;
 
 pub fn get_store(app: &tauri::AppHandle) -> Result<Arc<Store<Wry>>, String> {
@@ -66,6 +68,7 @@ pub fn create_library(app: tauri::AppHandle, name: &str, icon: &str, color: &str
         Ok(conn) => {
             let _ = fs::create_dir_all(full_path.join("originals"));
             let _ = fs::create_dir_all(full_path.join("thumbnails"));
+            let _ = fs::create_dir_all(full_path.join("previews"));
 
             conn.execute(
                 "CREATE TABLE IF NOT EXISTS item (
@@ -110,6 +113,8 @@ pub fn create_library(app: tauri::AppHandle, name: &str, icon: &str, color: &str
                 )",
                 [],
             ).map_err(|e| e.to_string())?;
+
+            schema::migrate(&conn)?;
         }
         Err(e) => return Err(e.to_string()),
     }
@@ -123,7 +128,8 @@ pub fn create_library(app: tauri::AppHandle, name: &str, icon: &str, color: &str
         "name": name,
         "icon": icon,
         "color": color,
-        "path": path
+        "path": path,
+        "write_sidecars": false
     });
 
     libraries.push(value.clone());
@@ -149,9 +155,43 @@ pub fn update_library_path(app: tauri::AppHandle, library_id: String, new_path:
     }
     store.set("libraries", Value::Array(libraries));
     save_store(store)?;
+    library::close_library(&library_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_library_write_sidecars(app: tauri::AppHandle, library_id: String, enabled: bool) -> Result<(), String> {
+    let store = get_store(&app)?;
+    let mut libraries = match store.get("libraries") {
+        Some(Value::Array(arr)) => arr.clone(),
+        _ => vec![],
+    };
+    for lib in libraries.iter_mut() {
+        if lib.get("id").and_then(|v| v.as_str()) == Some(library_id.as_str()) {
+            if let Some(obj) = lib.as_object_mut() {
+                obj.insert("write_sidecars".to_string(), Value::Bool(enabled));
+            }
+        }
+    }
+    store.set("libraries", Value::Array(libraries));
+    save_store(store)?;
     Ok(())
 }
 
+pub fn get_library_flag(app: &tauri::AppHandle, library_id: &str, key: &str) -> Result<bool, String> {
+    let store = get_store(app)?;
+    let libraries = match store.get("libraries") {
+        Some(Value::Array(arr)) => arr,
+        _ => vec![],
+    };
+    Ok(libraries
+        .iter()
+        .find(|lib| lib.get("id").and_then(|v| v.as_str()) == Some(library_id))
+        .and_then(|lib| lib.get(key))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false))
+}
+
 #[tauri::command]
 pub fn remove_library(app: tauri::AppHandle, library_id: String) -> Result<(), String> {
     let store = get_store(&app)?;
@@ -165,6 +205,7 @@ pub fn remove_library(app: tauri::AppHandle, library_id: String) -> Result<(), S
         .collect();
     store.set("libraries", Value::Array(filtered));
     save_store(store)?;
+    library::close_library(&library_id);
     Ok(())
 }
 
@@ -182,6 +223,7 @@ pub fn get_selected_library(app: tauri::AppHandle) -> Result<Option<String>, Str
 pub fn set_selected_library(app: tauri::AppHandle, library_id: Option<String>) -> Result<(), String> {
     let store = get_store(&app)?;
     if let Some(id) = library_id {
+        library::close_library(&id);
         store.set("selected_library", Value::String(id));
     } else {
         store.set("selected_library", Value::Null);
@@ -0,0 +1,146 @@
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use rusqlite::params;
+
+use crate::modules::journal;
+use crate::modules::library;
+use crate::modules::utils;
+
+const MAX_OFFSET_MINUTES: i32 = 14 * 60;
+
+#[tauri::command]
+pub fn set_items_date(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, value: DateTime<FixedOffset>) -> Result<(), String> {
+    let time_zone = value.offset().local_minus_utc() / 60;
+    check_offset(time_zone)?;
+    update_dates(&app, &library_id, &item_ids, "set_items_date", |_, _| Ok((value.with_timezone(&Utc), Some(time_zone))))
+}
+
+#[tauri::command]
+pub fn shift_items_date(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, seconds: i64) -> Result<(), String> {
+    let offset = Duration::try_seconds(seconds).ok_or_else(|| utils::treat_msg("The date offset is too large"))?;
+    update_dates(&app, &library_id, &item_ids, "shift_items_date", |created_at, time_zone| Ok((shift_date(created_at, offset)?, time_zone)))
+}
+
+#[tauri::command]
+pub fn set_items_time_zone(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, offset_minutes: i32, keep_local_time: bool) -> Result<(), String> {
+    check_offset(offset_minutes)?;
+    update_dates(&app, &library_id, &item_ids, "set_items_time_zone", |created_at, time_zone| {
+        Ok((change_time_zone(created_at, time_zone, offset_minutes, keep_local_time)?, Some(offset_minutes)))
+    })
+}
+
+fn check_offset(offset_minutes: i32) -> Result<(), String> {
+    if !(-MAX_OFFSET_MINUTES..=MAX_OFFSET_MINUTES).contains(&offset_minutes) {
+        return Err(utils::treat_msg("Time zone offsets must be between -14:00 and +14:00"));
+    }
+    Ok(())
+}
+
+fn change_time_zone(created_at: DateTime<Utc>, time_zone: Option<i32>, offset_minutes: i32, keep_local_time: bool) -> Result<DateTime<Utc>, String> {
+    if !keep_local_time {
+        return Ok(created_at);
+    }
+
+    let time_zone = time_zone.ok_or_else(|| utils::treat_msg("The local time of an item without a time zone is unknown"))?;
+    shift_date(created_at, Duration::minutes((time_zone - offset_minutes) as i64))
+}
+
+fn shift_date(created_at: DateTime<Utc>, offset: Duration) -> Result<DateTime<Utc>, String> {
+    created_at.checked_add_signed(offset).ok_or_else(|| utils::treat_msg("The shifted date is out of range"))
+}
+
+#[tauri::command]
+pub fn revert_items_date(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item", "id", &item_ids)?;
+    for item_id in &item_ids {
+        tx.execute(
+            "UPDATE item SET
+                created_at = original_created_at,
+                time_zone = original_time_zone,
+                original_created_at = NULL,
+                original_time_zone = NULL
+            WHERE id = ?1 AND original_created_at IS NOT NULL",
+            params![item_id],
+        ).map_err(|e| utils::treat(e, "Unable to revert the item date"))?;
+    }
+    journal::record(&tx, "revert_items_date", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save item dates"))?;
+    Ok(())
+}
+
+fn update_dates<F>(app: &tauri::AppHandle, library_id: &str, item_ids: &[String], name: &str, compute: F) -> Result<(), String>
+where
+    F: Fn(DateTime<Utc>, Option<i32>) -> Result<(DateTime<Utc>, Option<i32>), String>,
+{
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = library::get_db_connection(app, library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item", "id", item_ids)?;
+    for item_id in item_ids {
+        let (created_at, time_zone): (String, Option<i32>) = tx
+            .query_row("SELECT created_at, time_zone FROM item WHERE id = ?1", params![item_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| utils::treat(e, "Item not found"))?;
+        let created_at = DateTime::parse_from_rfc3339(&created_at).map_err(|e| utils::treat(e, "Invalid item date"))?.with_timezone(&Utc);
+        let (created_at, time_zone) = compute(created_at, time_zone)?;
+
+        tx.execute(
+            "UPDATE item SET
+                original_time_zone = CASE WHEN original_created_at IS NULL THEN time_zone ELSE original_time_zone END,
+                original_created_at = coalesce(original_created_at, created_at),
+                created_at = ?1,
+                time_zone = ?2
+            WHERE id = ?3",
+            params![created_at.to_rfc3339(), time_zone, item_id],
+        ).map_err(|e| utils::treat(e, "Unable to update the item date"))?;
+    }
+    journal::record(&tx, name, vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save item dates"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shifting_dates_moves_the_instant() {
+        let date = DateTime::parse_from_rfc3339("2024-03-01T10:00:00Z").unwrap().with_timezone(&Utc);
+        let shifted = shift_date(date, Duration::hours(-12)).unwrap();
+        assert_eq!(shifted.to_rfc3339(), "2024-02-29T22:00:00+00:00");
+    }
+
+    #[test]
+    fn shifting_past_the_supported_range_fails() {
+        let offset = Duration::try_seconds(i64::MAX / 1000).unwrap();
+        assert!(shift_date(DateTime::<Utc>::MAX_UTC, Duration::seconds(1)).is_err());
+        assert!(shift_date(DateTime::<Utc>::MIN_UTC, -Duration::seconds(1)).is_err());
+        assert!(shift_date(Utc::now(), offset).is_err());
+    }
+
+    #[test]
+    fn keeping_local_time_requires_a_time_zone() {
+        let date = DateTime::parse_from_rfc3339("2024-03-01T10:00:00Z").unwrap().with_timezone(&Utc);
+
+        assert_eq!(change_time_zone(date, Some(60), -300, true).unwrap().to_rfc3339(), "2024-03-01T16:00:00+00:00");
+        assert_eq!(change_time_zone(date, None, -300, false).unwrap(), date);
+        assert!(change_time_zone(date, None, -300, true).is_err());
+    }
+
+    #[test]
+    fn offsets_beyond_fourteen_hours_are_rejected() {
+        let offset = |value: &str| DateTime::parse_from_rfc3339(value).unwrap().offset().local_minus_utc() / 60;
+
+        assert!(check_offset(offset("2024-03-01T10:00:00+14:00")).is_ok());
+        assert!(check_offset(offset("2024-03-01T10:00:00-12:00")).is_ok());
+        assert!(check_offset(offset("2024-03-01T10:00:00+20:00")).is_err());
+        assert!(check_offset(-MAX_OFFSET_MINUTES - 1).is_err());
+    }
+}
\ No newline at end of file
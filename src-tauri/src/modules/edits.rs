@@ -0,0 +1,578 @@
+use image::{DynamicImage, ImageFormat, Rgba, Rgba32FImage};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::modules::embed;
+use crate::modules::export::{self, ExportFormat};
+use crate::modules::hash;
+use crate::modules::journal;
+use crate::modules::library;
+use crate::modules::utils;
+use crate::modules::xmp::Crop;
+
+const PREVIEW_SIZE: u32 = 2048;
+const WHITE_BALANCE_STRENGTH: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AspectRatio {
+    #[serde(rename = "original")]
+    Original,
+    #[serde(rename = "1:1")]
+    Square,
+    #[serde(rename = "5:4")]
+    FiveFour,
+    #[serde(rename = "4:3")]
+    FourThree,
+    #[serde(rename = "3:2")]
+    ThreeTwo,
+    #[serde(rename = "16:9")]
+    SixteenNine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EditOperation {
+    Rotate { degrees: u16 },
+    Flip { horizontal: bool },
+    Crop { left: f32, top: f32, right: f32, bottom: f32, aspect: Option<AspectRatio> },
+    Straighten { angle: f32 },
+    Exposure { value: f32 },
+    Contrast { value: f32 },
+    Saturation { value: f32 },
+    WhiteBalance { temperature: f32, tint: f32 },
+}
+
+impl AspectRatio {
+    fn long_to_short(&self, width: f32, height: f32) -> f32 {
+        match self {
+            AspectRatio::Original => width.max(height) / width.min(height),
+            AspectRatio::Square => 1.0,
+            AspectRatio::FiveFour => 5.0 / 4.0,
+            AspectRatio::FourThree => 4.0 / 3.0,
+            AspectRatio::ThreeTwo => 3.0 / 2.0,
+            AspectRatio::SixteenNine => 16.0 / 9.0,
+        }
+    }
+}
+
+impl EditOperation {
+    pub fn validate(&self) -> Result<(), String> {
+        let in_range = |value: f32, limit: f32| value.is_finite() && (-limit..=limit).contains(&value);
+        let valid = match *self {
+            EditOperation::Rotate { degrees } => [0, 90, 180, 270].contains(&degrees),
+            EditOperation::Flip { .. } => true,
+            EditOperation::Crop { left, top, right, bottom, .. } => (0.0..right).contains(&left) && right <= 1.0 && (0.0..bottom).contains(&top) && bottom <= 1.0,
+            EditOperation::Straighten { angle } => in_range(angle, 45.0),
+            EditOperation::Exposure { value } => in_range(value, 5.0),
+            EditOperation::Contrast { value } | EditOperation::Saturation { value } => in_range(value, 100.0),
+            EditOperation::WhiteBalance { temperature, tint } => in_range(temperature, 100.0) && in_range(tint, 100.0),
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(utils::treat_msg("One of the edits has an invalid value"))
+        }
+    }
+
+    fn apply(&self, image: DynamicImage) -> DynamicImage {
+        match *self {
+            EditOperation::Rotate { degrees } => match degrees {
+                90 => image.rotate90(),
+                180 => image.rotate180(),
+                270 => image.rotate270(),
+                _ => image,
+            },
+            EditOperation::Flip { horizontal } => if horizontal { image.fliph() } else { image.flipv() },
+            EditOperation::Crop { left, top, right, bottom, aspect } => crop(image, left, top, right, bottom, aspect),
+            EditOperation::Straighten { angle } => straighten(image, angle),
+            EditOperation::Exposure { value } => {
+                let gain = 2f32.powf(value);
+                map_pixels(image, |rgb| rgb.map(|c| linear_to_srgb(srgb_to_linear(c) * gain)))
+            }
+            EditOperation::Contrast { value } => {
+                let factor = 1.0 + value / 100.0;
+                map_pixels(image, |rgb| rgb.map(|c| (c - 0.5) * factor + 0.5))
+            }
+            EditOperation::Saturation { value } => {
+                let factor = 1.0 + value / 100.0;
+                map_pixels(image, |[r, g, b]| {
+                    let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                    [r, g, b].map(|c| luma + (c - luma) * factor)
+                })
+            }
+            EditOperation::WhiteBalance { temperature, tint } => {
+                let warmth = temperature / 100.0 * WHITE_BALANCE_STRENGTH;
+                let magenta = tint / 100.0 * WHITE_BALANCE_STRENGTH;
+                let gains = [1.0 + warmth, 1.0 - magenta, 1.0 - warmth];
+                map_pixels(image, |mut rgb| {
+                    for (c, gain) in rgb.iter_mut().zip(gains) {
+                        *c = linear_to_srgb(srgb_to_linear(*c) * gain);
+                    }
+                    rgb
+                })
+            }
+        }
+    }
+}
+
+pub fn apply_edits(image: DynamicImage, edits: &[EditOperation]) -> DynamicImage {
+    edits.iter().fold(image, |image, edit| edit.apply(image))
+}
+
+pub fn render_item(library_root: &Path, item: &utils::Item) -> Result<DynamicImage, String> {
+    let cache_path = render_cache_path(library_root, item)?;
+    if let Some(cache_path) = &cache_path {
+        if let Ok(cached) = image::open(cache_path) {
+            return Ok(cached);
+        }
+    }
+
+    let data = fs::read(utils::original_path(library_root, item)).map_err(|e| utils::treat(e, "Unable to read the original file"))?;
+    let image = export::decode_image(&data, &utils::file_extension(&item.original_name).to_lowercase())?;
+
+    let Some(cache_path) = cache_path else {
+        return Ok(image);
+    };
+
+    let edited = apply_edits(image, &item.edits);
+    clear_render_cache(library_root, &item.id)?;
+    fs::create_dir_all(library_root.join("renders")).map_err(|e| utils::treat(e, "Unable to create required directory"))?;
+    edited.save_with_format(&cache_path, ImageFormat::Png).map_err(|e| utils::treat(e, "Unable to cache the edited version"))?;
+
+    Ok(edited)
+}
+
+pub fn render_previews(library_root: &Path, item: &utils::Item) -> Result<(), String> {
+    let image = render_item(library_root, item)?;
+    let preview_path = library_root.join("previews").join(format!("{}.webp", item.id));
+    library::generate_thumbnail(&image, None, None, &library_root.join("thumbnails").join(format!("{}.webp", item.id)))?;
+
+    if item.edits.is_empty() {
+        if preview_path.exists() {
+            fs::remove_file(&preview_path).map_err(|e| utils::treat(e, "Unable to remove the edited preview"))?;
+        }
+        return Ok(());
+    }
+
+    let preview = export::encode_image(&export::fit_image(image, Some(PREVIEW_SIZE)), ExportFormat::Webp, 90)?;
+    fs::write(preview_path, preview).map_err(|e| utils::treat(e, "Unable to write the edited preview"))
+}
+
+pub fn clear_render_cache(library_root: &Path, item_id: &str) -> Result<(), String> {
+    let Ok(entries) = fs::read_dir(library_root.join("renders")) else {
+        return Ok(());
+    };
+
+    let prefix = format!("{}_", item_id);
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            fs::remove_file(entry.path()).map_err(|e| utils::treat(e, "Unable to clear the render cache"))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn render_cache_path(library_root: &Path, item: &utils::Item) -> Result<Option<PathBuf>, String> {
+    if item.edits.is_empty() {
+        return Ok(None);
+    }
+
+    let edits_json = serde_json::to_string(&item.edits).map_err(|e| utils::treat(e, "Unable to save the edits"))?;
+    let file_name = format!("{}_{:x}.png", item.id, md5::compute(edits_json));
+    Ok(Some(library_root.join("renders").join(file_name)))
+}
+
+#[tauri::command]
+pub fn get_item_edits(app: tauri::AppHandle, library_id: String, item_id: String) -> Result<Vec<EditOperation>, String> {
+    let conn = library::get_db_connection(&app, &library_id)?;
+    let item = conn
+        .query_row("SELECT * FROM item WHERE id = ?1", params![item_id], utils::deserialize_item)
+        .map_err(|e| utils::treat(e, "Item not found"))?;
+
+    Ok(item.edits)
+}
+
+#[tauri::command]
+pub async fn set_item_edits(app: tauri::AppHandle, library_id: String, item_id: String, edits: Vec<EditOperation>) -> Result<(), String> {
+    for edit in &edits {
+        edit.validate()?;
+    }
+
+    let library_root = library::get_library_root_path(&app, &library_id)?;
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let edits_json = edits_to_json(&edits)?;
+
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item", "id", std::slice::from_ref(&item_id))?;
+    let updated = tx.execute(
+        "UPDATE item SET edits = ?1 WHERE id = ?2",
+        params![edits_json, item_id],
+    ).map_err(|e| utils::treat(e, "Unable to save the edits"))?;
+
+    if updated == 0 {
+        return Err(utils::treat_msg("Item not found"));
+    }
+
+    journal::record(&tx, "set_item_edits", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save the edits"))?;
+
+    let item = conn
+        .query_row("SELECT * FROM item WHERE id = ?1", params![item_id], utils::deserialize_item)
+        .map_err(|e| utils::treat(e, "Item not found"))?;
+    clear_render_cache(&library_root, &item.id)?;
+    render_previews(&library_root, &item)
+}
+
+#[tauri::command]
+pub async fn revert_item_edits(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+
+    let library_root = library::get_library_root_path(&app, &library_id)?;
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item", "id", &item_ids)?;
+    for item_id in &item_ids {
+        tx.execute(
+            "UPDATE item SET edits = NULL WHERE id = ?1",
+            params![item_id],
+        ).map_err(|e| utils::treat(e, "Unable to revert the edits"))?;
+    }
+    journal::record(&tx, "revert_item_edits", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to revert the edits"))?;
+
+    for item_id in &item_ids {
+        clear_render_cache(&library_root, item_id)?;
+        let item = conn
+            .query_row("SELECT * FROM item WHERE id = ?1", params![item_id], utils::deserialize_item)
+            .map_err(|e| utils::treat(e, "Item not found"))?;
+        render_previews(&library_root, &item)?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn rotate_items(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, degrees: u16, lossless: bool) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+    if ![90, 180, 270].contains(&degrees) {
+        return Err(utils::treat_msg("Items can only be rotated by 90, 180 or 270 degrees"));
+    }
+
+    let library_root = library::get_library_root_path(&app, &library_id)?;
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let mut moves = Vec::new();
+    let items = match rotate_in_transaction(&mut conn, &library_root, &item_ids, degrees, lossless, &mut moves) {
+        Ok(items) => items,
+        Err(e) => {
+            for (rotated, _) in moves.iter().filter(|(from, _)| from.starts_with("trash/")) {
+                let _ = fs::remove_file(library_root.join(rotated));
+            }
+            return Err(e);
+        }
+    };
+
+    for item in &items {
+        clear_render_cache(&library_root, &item.id)?;
+        render_previews(&library_root, item)?;
+    }
+
+    Ok(())
+}
+
+fn rotate_in_transaction(
+    conn: &mut Connection,
+    library_root: &Path,
+    item_ids: &[String],
+    degrees: u16,
+    lossless: bool,
+    moves: &mut Vec<(String, String)>,
+) -> Result<Vec<utils::Item>, String> {
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item", "id", item_ids)?;
+    let mut items = Vec::new();
+
+    for item_id in item_ids {
+        let mut item = tx
+            .query_row("SELECT * FROM item WHERE id = ?1", params![item_id], utils::deserialize_item)
+            .map_err(|e| utils::treat(e, "Item not found"))?;
+
+        let rewritten = if lossless { rotate_jpeg_original(library_root, &mut item, degrees)? } else { None };
+        match rewritten {
+            Some(rotated) => {
+                let original = format!("originals/{}", utils::original_file_name(&item));
+                moves.push((original.clone(), format!("trash/{}.{}", Uuid::new_v4(), utils::file_extension(&item.original_name))));
+                moves.push((rotated, original));
+                rotate_crops(&mut item.edits, &mut item.crop, degrees);
+            }
+            None => push_rotation(&mut item.edits, degrees),
+        }
+        if degrees != 180 {
+            std::mem::swap(&mut item.width, &mut item.height);
+        }
+
+        tx.execute(
+            "UPDATE item SET width = ?1, height = ?2, checksum = ?3, checksum_algorithm = ?4, edits = ?5, crop = ?6 WHERE id = ?7",
+            params![
+                item.width,
+                item.height,
+                item.checksum,
+                item.checksum_algorithm.name(),
+                edits_to_json(&item.edits)?,
+                item.crop.and_then(|crop| serde_json::to_string(&crop).ok()),
+                item.id
+            ],
+        ).map_err(|e| utils::treat(e, "Unable to rotate the item"))?;
+        items.push(item);
+    }
+
+    journal::record_with_moves(&tx, "rotate_items", vec![snapshot], moves.clone())?;
+    journal::move_files(library_root, moves)?;
+    if let Err(e) = tx.commit() {
+        let restored: Vec<(String, String)> = moves.iter().rev().map(|(from, to)| (to.clone(), from.clone())).collect();
+        if let Err(e) = journal::move_files(library_root, &restored) {
+            log::warn!("Unable to restore the original files: {}", e);
+        }
+        return Err(utils::treat(e, "Unable to rotate the items"));
+    }
+
+    Ok(items)
+}
+
+fn push_rotation(edits: &mut Vec<EditOperation>, degrees: u16) {
+    if let Some(EditOperation::Rotate { degrees: previous }) = edits.last().copied() {
+        edits.pop();
+        let total = (previous + degrees) % 360;
+        if total != 0 {
+            edits.push(EditOperation::Rotate { degrees: total });
+        }
+    } else {
+        edits.push(EditOperation::Rotate { degrees });
+    }
+}
+
+fn rotate_crops(edits: &mut [EditOperation], crop: &mut Option<Crop>, degrees: u16) {
+    for edit in edits {
+        match edit {
+            EditOperation::Crop { left, top, right, bottom, .. } => {
+                (*left, *top, *right, *bottom) = rotate_rect((*left, *top, *right, *bottom), degrees);
+            }
+            EditOperation::Flip { horizontal } if degrees != 180 => *horizontal = !*horizontal,
+            _ => {}
+        }
+    }
+
+    if let Some(crop) = crop {
+        (crop.left, crop.top, crop.right, crop.bottom) = rotate_rect((crop.left, crop.top, crop.right, crop.bottom), degrees);
+    }
+}
+
+fn rotate_rect((left, top, right, bottom): (f32, f32, f32, f32), degrees: u16) -> (f32, f32, f32, f32) {
+    match degrees {
+        90 => (1.0 - bottom, left, 1.0 - top, right),
+        180 => (1.0 - right, 1.0 - bottom, 1.0 - left, 1.0 - top),
+        270 => (top, 1.0 - right, bottom, 1.0 - left),
+        _ => (left, top, right, bottom),
+    }
+}
+
+fn rotate_jpeg_original(library_root: &Path, item: &mut utils::Item, degrees: u16) -> Result<Option<String>, String> {
+    if item.source_path.is_some() || !["jpg", "jpeg"].contains(&utils::file_extension(&item.original_name).to_lowercase().as_str()) {
+        return Ok(None);
+    }
+
+    let original_path = utils::original_path(library_root, item);
+    let mut data = fs::read(&original_path).map_err(|e| utils::treat(e, "Unable to read the original file"))?;
+    let Some(orientation) = embed::jpeg_orientation(&data) else {
+        return Ok(None);
+    };
+
+    let rotated = (0..degrees / 90).fold(orientation, |orientation, _| rotate_orientation(orientation));
+    embed::set_jpeg_orientation(&mut data, rotated)?;
+
+    let rotated_path = format!("trash/{}.{}", Uuid::new_v4(), utils::file_extension(&item.original_name));
+    fs::create_dir_all(library_root.join("trash")).map_err(|e| utils::treat(e, "Unable to create required directory"))?;
+    fs::write(library_root.join(&rotated_path), &data).map_err(|e| utils::treat(e, "Unable to update the original file"))?;
+    item.checksum = hash::hash_bytes(&data, hash::CURRENT_ALGORITHM);
+    item.checksum_algorithm = hash::CURRENT_ALGORITHM;
+
+    Ok(Some(rotated_path))
+}
+
+fn rotate_orientation(orientation: u16) -> u16 {
+    match orientation {
+        1 => 6,
+        6 => 3,
+        3 => 8,
+        8 => 1,
+        2 => 7,
+        7 => 4,
+        4 => 5,
+        5 => 2,
+        _ => 6,
+    }
+}
+
+fn edits_to_json(edits: &[EditOperation]) -> Result<Option<String>, String> {
+    if edits.is_empty() {
+        return Ok(None);
+    }
+
+    serde_json::to_string(edits).map(Some).map_err(|e| utils::treat(e, "Unable to save the edits"))
+}
+
+fn crop(image: DynamicImage, left: f32, top: f32, right: f32, bottom: f32, aspect: Option<AspectRatio>) -> DynamicImage {
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let (mut x, mut y) = (left * width, top * height);
+    let (mut w, mut h) = ((right - left) * width, (bottom - top) * height);
+
+    if let Some(aspect) = aspect {
+        let ratio = aspect.long_to_short(width, height);
+        let ratio = if h > w { 1.0 / ratio } else { ratio };
+        let (target_w, target_h) = if w / h > ratio { (h * ratio, h) } else { (w, w / ratio) };
+        x += (w - target_w) / 2.0;
+        y += (h - target_h) / 2.0;
+        w = target_w;
+        h = target_h;
+    }
+
+    image.crop_imm(x.round() as u32, y.round() as u32, (w.round() as u32).max(1), (h.round() as u32).max(1))
+}
+
+fn straighten(image: DynamicImage, angle: f32) -> DynamicImage {
+    if angle == 0.0 {
+        return image;
+    }
+
+    let has_alpha = image.color().has_alpha();
+    let source = image.to_rgba32f();
+    let (width, height) = (source.width() as f32, source.height() as f32);
+    let (sin, cos) = angle.to_radians().sin_cos();
+
+    let scale = (width / (width * cos + height * sin.abs())).min(height / (width * sin.abs() + height * cos));
+    let output_width = ((width * scale).floor() as u32).max(1);
+    let output_height = ((height * scale).floor() as u32).max(1);
+
+    let output = Rgba32FImage::from_fn(output_width, output_height, |x, y| {
+        let dx = x as f32 + 0.5 - output_width as f32 / 2.0;
+        let dy = y as f32 + 0.5 - output_height as f32 / 2.0;
+        sample_bilinear(&source, cos * dx - sin * dy + width / 2.0 - 0.5, sin * dx + cos * dy + height / 2.0 - 0.5)
+    });
+
+    to_display(output, has_alpha)
+}
+
+fn sample_bilinear(image: &Rgba32FImage, x: f32, y: f32) -> Rgba<f32> {
+    let max_x = image.width() as f32 - 1.0;
+    let max_y = image.height() as f32 - 1.0;
+    let (x, y) = (x.clamp(0.0, max_x), y.clamp(0.0, max_y));
+    let (x0, y0) = (x.floor(), y.floor());
+    let (x1, y1) = ((x0 + 1.0).min(max_x), (y0 + 1.0).min(max_y));
+    let (fx, fy) = (x - x0, y - y0);
+
+    let top_left = image.get_pixel(x0 as u32, y0 as u32);
+    let top_right = image.get_pixel(x1 as u32, y0 as u32);
+    let bottom_left = image.get_pixel(x0 as u32, y1 as u32);
+    let bottom_right = image.get_pixel(x1 as u32, y1 as u32);
+
+    let mut pixel = [0.0; 4];
+    for (channel, value) in pixel.iter_mut().enumerate() {
+        let top = top_left[channel] * (1.0 - fx) + top_right[channel] * fx;
+        let bottom = bottom_left[channel] * (1.0 - fx) + bottom_right[channel] * fx;
+        *value = top * (1.0 - fy) + bottom * fy;
+    }
+
+    Rgba(pixel)
+}
+
+fn map_pixels<F: Fn([f32; 3]) -> [f32; 3]>(image: DynamicImage, f: F) -> DynamicImage {
+    let has_alpha = image.color().has_alpha();
+    let mut buffer = image.to_rgba32f();
+
+    for pixel in buffer.pixels_mut() {
+        let [r, g, b] = f([pixel[0], pixel[1], pixel[2]]);
+        pixel[0] = r.clamp(0.0, 1.0);
+        pixel[1] = g.clamp(0.0, 1.0);
+        pixel[2] = b.clamp(0.0, 1.0);
+    }
+
+    to_display(buffer, has_alpha)
+}
+
+fn to_display(buffer: Rgba32FImage, has_alpha: bool) -> DynamicImage {
+    let image = DynamicImage::ImageRgba32F(buffer);
+    if has_alpha {
+        DynamicImage::ImageRgba8(image.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn four_quarter_turns_restore_the_orientation() {
+        for orientation in 1..=8 {
+            let rotated = (0..4).fold(orientation, |orientation, _| rotate_orientation(orientation));
+            assert_eq!(rotated, orientation);
+            assert_ne!(rotate_orientation(orientation), orientation);
+        }
+    }
+
+    #[test]
+    fn rotations_are_combined() {
+        let mut edits = Vec::new();
+        push_rotation(&mut edits, 90);
+        push_rotation(&mut edits, 180);
+        assert!(matches!(edits.as_slice(), [EditOperation::Rotate { degrees: 270 }]));
+
+        push_rotation(&mut edits, 90);
+        assert!(edits.is_empty());
+    }
+    #[test]
+    fn lossless_rotations_keep_the_edited_region() {
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(40, 20, |x, y| image::Rgb([x as u8 * 6, y as u8 * 12, (x + y) as u8])));
+        let edits = vec![
+            EditOperation::Crop { left: 0.1, top: 0.25, right: 0.5, bottom: 0.75, aspect: None },
+            EditOperation::Flip { horizontal: true },
+        ];
+
+        for degrees in [90, 180, 270] {
+            let expected = apply_edits(image.clone(), &[edits.clone(), vec![EditOperation::Rotate { degrees }]].concat());
+            let mut rotated_edits = edits.clone();
+            let mut crop = Some(Crop { left: 0.0, top: 0.0, right: 0.5, bottom: 0.25, angle: 0.0 });
+            rotate_crops(&mut rotated_edits, &mut crop, degrees);
+
+            let rotated = apply_edits(image.clone(), &[EditOperation::Rotate { degrees }]);
+            assert_eq!(apply_edits(rotated, &rotated_edits).to_rgb8(), expected.to_rgb8());
+            let crop = crop.unwrap();
+            assert!(((crop.right - crop.left) * (crop.bottom - crop.top) - 0.125).abs() < 1e-6);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,373 @@
+use libheif_rs::HeifContext;
+
+use crate::modules::iptc::{IptcData, PHOTOSHOP_HEADER};
+use crate::modules::utils;
+use crate::modules::xmp::XmpData;
+
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+
+pub fn supports_embedding(ext: &str) -> bool {
+    matches!(ext.to_lowercase().as_str(), "jpg" | "jpeg" | "png" | "heic" | "heif")
+}
+
+pub fn embed_metadata(data: &[u8], ext: &str, xmp: &XmpData, keep_existing_xmp: bool) -> Result<Vec<u8>, String> {
+    match ext.to_lowercase().as_str() {
+        "jpg" | "jpeg" => embed_jpeg(data, xmp, keep_existing_xmp),
+        "png" => embed_png(data, xmp),
+        "heic" | "heif" => embed_heif(data, xmp),
+        _ => Err(utils::treat_msg("Metadata cannot be embedded into this file type")),
+    }
+}
+
+fn embed_jpeg(data: &[u8], xmp: &XmpData, keep_existing_xmp: bool) -> Result<Vec<u8>, String> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err(utils::treat_msg("Invalid JPEG file"));
+    }
+
+    let iptc = IptcData {
+        title: xmp.title.clone(),
+        caption: xmp.description.clone(),
+        keywords: xmp.keywords.iter().map(|keyword| keyword.rsplit('/').next().unwrap_or(keyword).to_string()).collect(),
+    };
+    let merge_existing = keep_existing_xmp && has_xmp_segment(&data[2..]);
+    let mut merged = false;
+    let mut output = vec![0xFF, 0xD8];
+    let mut offset = 2;
+    let mut inserted = false;
+
+    while offset + 4 <= data.len() && data[offset] == 0xFF {
+        let marker = data[offset + 1];
+        if marker == 0xDA {
+            break;
+        }
+
+        let length = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        let end = offset + 2 + length;
+        let body = data.get(offset + 4..end).ok_or_else(|| utils::treat_msg("Invalid JPEG file"))?;
+        let is_leading = marker == 0xE0 || (marker == 0xE1 && body.starts_with(b"Exif\0"));
+        let is_xmp = marker == 0xE1 && body.starts_with(XMP_HEADER);
+        let is_photoshop = marker == 0xED && body.starts_with(PHOTOSHOP_HEADER);
+
+        if !inserted && !is_leading {
+            if !merge_existing {
+                output.extend(jpeg_segment(0xE1, &[XMP_HEADER, xmp.to_packet().as_bytes()].concat())?);
+            }
+            output.extend(jpeg_segment(0xED, &iptc.to_photoshop_segment(data))?);
+            inserted = true;
+        }
+
+        if is_xmp && merge_existing && !merged {
+            let packet = String::from_utf8_lossy(&body[XMP_HEADER.len()..]);
+            output.extend(jpeg_segment(0xE1, &[XMP_HEADER, xmp.merge_into(&packet).as_bytes()].concat())?);
+            merged = true;
+        } else if !is_xmp && !is_photoshop {
+            output.extend_from_slice(&data[offset..end]);
+        }
+
+        offset = end;
+    }
+
+    if !inserted {
+        output.extend(jpeg_segment(0xE1, &[XMP_HEADER, xmp.to_packet().as_bytes()].concat())?);
+        output.extend(jpeg_segment(0xED, &iptc.to_photoshop_segment(data))?);
+    }
+
+    output.extend_from_slice(&data[offset..]);
+    Ok(output)
+}
+
+pub fn embed_exif(data: &[u8], ext: &str, tiff: &[u8]) -> Result<Vec<u8>, String> {
+    match ext.to_lowercase().as_str() {
+        "jpg" | "jpeg" => {
+            if !data.starts_with(&[0xFF, 0xD8]) {
+                return Err(utils::treat_msg("Invalid JPEG file"));
+            }
+
+            let mut offset = 2;
+            if data.get(2..4) == Some(&[0xFF, 0xE0]) && data.len() >= 6 {
+                offset = (4 + u16::from_be_bytes([data[4], data[5]]) as usize).min(data.len());
+            }
+
+            let mut output = data[..offset].to_vec();
+            output.extend(jpeg_segment(0xE1, &[b"Exif\0\0", tiff].concat())?);
+            output.extend_from_slice(&data[offset..]);
+            Ok(output)
+        }
+        "png" => {
+            let ihdr_end = PNG_SIGNATURE.len() + 25;
+            if !data.starts_with(PNG_SIGNATURE) || data.get(PNG_SIGNATURE.len() + 4..PNG_SIGNATURE.len() + 8) != Some(b"IHDR") || data.len() < ihdr_end {
+                return Err(utils::treat_msg("Invalid PNG file"));
+            }
+
+            let mut output = data[..ihdr_end].to_vec();
+            output.extend(png_chunk(b"eXIf", tiff));
+            output.extend_from_slice(&data[ihdr_end..]);
+            Ok(output)
+        }
+        "webp" => embed_webp_exif(data, tiff),
+        _ => Err(utils::treat_msg("EXIF cannot be embedded into this file type")),
+    }
+}
+
+fn embed_webp_exif(data: &[u8], tiff: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 30 || &data[..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return Err(utils::treat_msg("Invalid WebP file"));
+    }
+
+    let mut chunks = match &data[12..16] {
+        b"VP8X" => {
+            let mut chunks = data[12..].to_vec();
+            chunks[8] |= 0x08;
+            chunks
+        }
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(data[21..25].try_into().unwrap());
+            let alpha = if (bits >> 28) & 1 == 1 { 0x10 } else { 0 };
+            [webp_extended_header(alpha, (bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1), data[12..].to_vec()].concat()
+        }
+        b"VP8 " => {
+            let width = u16::from_le_bytes([data[26], data[27]]) as u32 & 0x3FFF;
+            let height = u16::from_le_bytes([data[28], data[29]]) as u32 & 0x3FFF;
+            [webp_extended_header(0, width, height), data[12..].to_vec()].concat()
+        }
+        _ => return Err(utils::treat_msg("Invalid WebP file")),
+    };
+
+    chunks.extend_from_slice(b"EXIF");
+    chunks.extend_from_slice(&(tiff.len() as u32).to_le_bytes());
+    chunks.extend_from_slice(tiff);
+    if tiff.len() % 2 == 1 {
+        chunks.push(0);
+    }
+
+    let mut output = b"RIFF".to_vec();
+    output.extend_from_slice(&((chunks.len() + 4) as u32).to_le_bytes());
+    output.extend_from_slice(b"WEBP");
+    output.extend(chunks);
+    Ok(output)
+}
+
+fn webp_extended_header(flags: u8, width: u32, height: u32) -> Vec<u8> {
+    let mut header = b"VP8X".to_vec();
+    header.extend_from_slice(&10u32.to_le_bytes());
+    header.extend_from_slice(&[flags | 0x08, 0, 0, 0]);
+    header.extend_from_slice(&width.saturating_sub(1).to_le_bytes()[..3]);
+    header.extend_from_slice(&height.saturating_sub(1).to_le_bytes()[..3]);
+    header
+}
+
+pub fn jpeg_orientation(data: &[u8]) -> Option<u16> {
+    let (offset, big_endian) = find_jpeg_orientation(data)?;
+    Some(read_u16(data, offset, big_endian))
+}
+
+pub fn set_jpeg_orientation(data: &mut [u8], orientation: u16) -> Result<(), String> {
+    let (offset, big_endian) = find_jpeg_orientation(data).ok_or_else(|| utils::treat_msg("The file has no EXIF orientation"))?;
+    let bytes = if big_endian { orientation.to_be_bytes() } else { orientation.to_le_bytes() };
+    data[offset..offset + 2].copy_from_slice(&bytes);
+    Ok(())
+}
+
+fn find_jpeg_orientation(data: &[u8]) -> Option<(usize, bool)> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut offset = 2;
+    while offset + 4 <= data.len() && data[offset] == 0xFF && data[offset + 1] != 0xDA {
+        let length = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        let end = (offset + 2 + length).min(data.len());
+
+        if data[offset + 1] == 0xE1 && data.get(offset + 4..end).is_some_and(|body| body.starts_with(b"Exif\0\0")) {
+            let tiff = offset + 10;
+            let big_endian = match data.get(tiff..tiff + 2)? {
+                b"MM" => true,
+                b"II" => false,
+                _ => return None,
+            };
+            let ifd = tiff + read_u32(&data[..end], tiff + 4, big_endian)? as usize;
+            if ifd + 2 > end {
+                return None;
+            }
+
+            let count = read_u16(data, ifd, big_endian) as usize;
+
+            for index in 0..count {
+                let entry = ifd + 2 + index * 12;
+                if entry + 12 > end {
+                    return None;
+                }
+                if read_u16(data, entry, big_endian) == 0x0112 && read_u16(data, entry + 2, big_endian) == 3 {
+                    return Some((entry + 8, big_endian));
+                }
+            }
+
+            return None;
+        }
+
+        offset += 2 + length;
+    }
+
+    None
+}
+
+fn read_u16(data: &[u8], offset: usize, big_endian: bool) -> u16 {
+    let bytes = [data[offset], data[offset + 1]];
+    if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+}
+
+fn read_u32(data: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = data.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+}
+
+fn has_xmp_segment(data: &[u8]) -> bool {
+    let mut offset = 0;
+
+    while offset + 4 <= data.len() && data[offset] == 0xFF && data[offset + 1] != 0xDA {
+        let length = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        if data[offset + 1] == 0xE1 && data.get(offset + 4..offset + 2 + length).is_some_and(|body| body.starts_with(XMP_HEADER)) {
+            return true;
+        }
+        offset += 2 + length;
+    }
+
+    false
+}
+
+fn jpeg_segment(marker: u8, body: &[u8]) -> Result<Vec<u8>, String> {
+    if body.len() + 2 > u16::MAX as usize {
+        return Err(utils::treat_msg("The metadata is too large to be embedded"));
+    }
+
+    let mut segment = vec![0xFF, marker];
+    segment.extend_from_slice(&((body.len() + 2) as u16).to_be_bytes());
+    segment.extend_from_slice(body);
+    Ok(segment)
+}
+
+fn embed_png(data: &[u8], xmp: &XmpData) -> Result<Vec<u8>, String> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return Err(utils::treat_msg("Invalid PNG file"));
+    }
+
+    let mut output = PNG_SIGNATURE.to_vec();
+    let mut offset = PNG_SIGNATURE.len();
+
+    while offset + 12 <= data.len() {
+        let length = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let end = offset + 12 + length;
+        if end > data.len() {
+            return Err(utils::treat_msg("Invalid PNG file"));
+        }
+
+        let chunk_type = &data[offset + 4..offset + 8];
+        let chunk_data = &data[offset + 8..offset + 8 + length];
+
+        let is_xmp = chunk_type == b"iTXt" && chunk_data.starts_with(PNG_XMP_KEYWORD) && chunk_data.get(PNG_XMP_KEYWORD.len()) == Some(&0);
+        if !is_xmp {
+            output.extend_from_slice(&data[offset..end]);
+        }
+
+        if chunk_type == b"IHDR" {
+            let mut text = PNG_XMP_KEYWORD.to_vec();
+            text.extend_from_slice(&[0, 0, 0, 0, 0]);
+            text.extend_from_slice(xmp.to_packet().as_bytes());
+            output.extend(png_chunk(b"iTXt", &text));
+        }
+
+        if chunk_type == b"IEND" {
+            output.extend_from_slice(&data[end..]);
+            return Ok(output);
+        }
+
+        offset = end;
+    }
+
+    Err(utils::treat_msg("Invalid PNG file"))
+}
+
+fn png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+    chunk.extend_from_slice(chunk_type);
+    chunk.extend_from_slice(data);
+    chunk.extend_from_slice(&crc32fast::hash(&chunk[4..]).to_be_bytes());
+    chunk
+}
+
+fn embed_heif(data: &[u8], xmp: &XmpData) -> Result<Vec<u8>, String> {
+    let mut ctx = HeifContext::read_from_bytes(data).map_err(|e| utils::treat(e, "Unable to read the HEIF container"))?;
+    let handle = ctx.primary_image_handle().map_err(|e| utils::treat(e, "Unable to read the HEIF container"))?;
+
+    ctx.add_xmp_metadata(&handle, xmp.to_packet().as_bytes()).map_err(|e| utils::treat(e, "Unable to embed metadata"))?;
+    ctx.write_to_bytes().map_err(|e| utils::treat(e, "Unable to embed metadata"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png() -> Vec<u8> {
+        let mut data = PNG_SIGNATURE.to_vec();
+        data.extend(png_chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]));
+        data.extend(png_chunk(b"IDAT", &[0x78, 0x9C, 0x63, 0x60, 0, 0, 0, 0x02, 0, 0x01]));
+        data.extend(png_chunk(b"IEND", &[]));
+        data
+    }
+
+    #[test]
+    fn png_receives_an_xmp_chunk() {
+        let xmp = XmpData { rating: Some(5), title: Some("Harbour".to_string()), ..Default::default() };
+        let output = embed_metadata(&png(), "png", &xmp, false).unwrap();
+        let again = embed_metadata(&output, "png", &xmp, false).unwrap();
+        let parsed = XmpData::from_file_data(&again).unwrap();
+
+        assert_eq!(parsed.rating, Some(5));
+        assert_eq!(parsed.title.as_deref(), Some("Harbour"));
+        assert_eq!(output, again);
+        assert!(again.ends_with(&png_chunk(b"IEND", &[])));
+    }
+
+    #[test]
+    fn truncated_png_is_rejected() {
+        let data = png();
+
+        for length in [data.len() - 2, data.len() - 14, data.len() - 20] {
+            assert!(embed_metadata(&data[..length], "png", &XmpData::default(), false).is_err());
+        }
+    }
+
+    #[test]
+    fn jpeg_keeps_other_photoshop_resources() {
+        let mut resources = b"8BIM\x04\x09\0\0\0\0\0\x04thmb".to_vec();
+        resources.extend_from_slice(&IptcData { title: Some("Old".to_string()), ..Default::default() }.to_photoshop_segment(&[])[PHOTOSHOP_HEADER.len()..]);
+        let mut data = vec![0xFF, 0xD8];
+        data.extend(jpeg_segment(0xED, &[PHOTOSHOP_HEADER, &resources].concat()).unwrap());
+        data.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0xFF, 0xD9]);
+
+        let xmp = XmpData { title: Some("New".to_string()), ..Default::default() };
+        let output = embed_metadata(&data, "jpg", &xmp, false).unwrap();
+
+        assert_eq!(output.windows(4).filter(|window| *window == b"8BIM").count(), 2);
+        assert!(output.windows(4).any(|window| window == b"thmb"));
+        assert_eq!(IptcData::from_jpeg(&output).unwrap().title.as_deref(), Some("New"));
+        assert_eq!(XmpData::from_file_data(&output).unwrap().title.as_deref(), Some("New"));
+    }
+
+    #[test]
+    fn existing_jpeg_xmp_is_updated_in_place() {
+        let old = XmpData { rating: Some(1), label: Some("Blue".to_string()), ..Default::default() };
+        let mut data = vec![0xFF, 0xD8];
+        data.extend(jpeg_segment(0xE1, &[XMP_HEADER, old.to_packet().as_bytes()].concat()).unwrap());
+        data.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0xFF, 0xD9]);
+
+        let output = embed_metadata(&data, "jpg", &XmpData { rating: Some(4), ..Default::default() }, true).unwrap();
+        let parsed = XmpData::from_file_data(&output).unwrap();
+
+        assert_eq!(parsed.rating, Some(4));
+        assert_eq!(parsed.label.as_deref(), Some("Blue"));
+        assert_eq!(String::from_utf8_lossy(&output).matches("<rdf:Description").count(), 1);
+    }
+}
\ No newline at end of file
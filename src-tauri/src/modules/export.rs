@@ -0,0 +1,336 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use tauri::Emitter;
+
+use crate::modules::color;
+use crate::modules::edits;
+use crate::modules::embed;
+use crate::modules::library;
+use crate::modules::sidecar;
+use crate::modules::utils;
+
+const REWRITTEN_EXIF_TAGS: [exif::Tag; 12] = [
+    exif::Tag::Orientation,
+    exif::Tag::ImageWidth,
+    exif::Tag::ImageLength,
+    exif::Tag::PixelXDimension,
+    exif::Tag::PixelYDimension,
+    exif::Tag::BitsPerSample,
+    exif::Tag::Compression,
+    exif::Tag::PhotometricInterpretation,
+    exif::Tag::SamplesPerPixel,
+    exif::Tag::PlanarConfiguration,
+    exif::Tag::RowsPerStrip,
+    exif::Tag::MakerNote,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Jpeg,
+    Webp,
+    Png,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataStrip {
+    #[default]
+    None,
+    Location,
+    All,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub original: bool,
+    pub embed_metadata: bool,
+    pub name_template: Option<String>,
+    pub max_size: Option<u32>,
+    pub format: Option<ExportFormat>,
+    pub quality: Option<u8>,
+    pub strip: MetadataStrip,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ExportProgress {
+    pub item_id: String,
+    pub completed: usize,
+    pub total: usize,
+}
+
+impl ExportFormat {
+    fn from_extension(ext: &str) -> Option<ExportFormat> {
+        match ext.to_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(ExportFormat::Jpeg),
+            "webp" => Some(ExportFormat::Webp),
+            "png" => Some(ExportFormat::Png),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Jpeg => "jpg",
+            ExportFormat::Webp => "webp",
+            ExportFormat::Png => "png",
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn export_items(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, destination: String, options: Option<ExportOptions>) -> Result<Vec<String>, String> {
+    let options = options.unwrap_or_default();
+    let library_root = library::get_library_root_path(&app, &library_id)?;
+    let destination = Path::new(&destination);
+    fs::create_dir_all(destination).map_err(|e| utils::treat(e, "Unable to create the export directory"))?;
+
+    let conn = library::get_db_connection(&app, &library_id)?;
+    let quality = options.quality.unwrap_or(90).clamp(1, 100);
+    let rerender = options.format.is_some() || options.max_size.is_some() || options.strip != MetadataStrip::None;
+    let write_metadata = match options.strip {
+        MetadataStrip::None => options.embed_metadata,
+        MetadataStrip::Location => true,
+        MetadataStrip::All => false,
+    };
+    let mut used_names = HashSet::new();
+    let mut exported = Vec::new();
+
+    for (index, item_id) in item_ids.iter().enumerate() {
+        let item = conn
+            .query_row("SELECT * FROM item WHERE id = ?1", params![item_id], utils::deserialize_item)
+            .map_err(|e| utils::treat(e, "Item not found"))?;
+        let source_ext = utils::file_extension(&item.original_name).to_lowercase();
+        let format = options.format.or_else(|| ExportFormat::from_extension(&source_ext)).unwrap_or(ExportFormat::Jpeg);
+        let edited = !options.original && !item.edits.is_empty();
+
+        let original = fs::read(utils::original_path(&library_root, &item)).map_err(|e| utils::treat(e, "Unable to read the original file"))?;
+        let (data, ext) = if edited || rerender {
+            let image = if edited { edits::render_item(&library_root, &item)? } else { decode_image(&original, &source_ext)? };
+            let ext = format.extension().to_string();
+            let mut data = encode_image(&fit_image(image, options.max_size), format, quality)?;
+
+            if options.strip != MetadataStrip::All {
+                if let Some(tiff) = export_exif(&original, options.strip == MetadataStrip::Location)? {
+                    data = embed::embed_exif(&data, &ext, &tiff)?;
+                }
+            }
+
+            (data, ext)
+        } else {
+            (original, source_ext)
+        };
+
+        let file_name = match &options.name_template {
+            Some(template) => format!("{}.{}", apply_template(&conn, template, &item, index + 1, item_ids.len())?, ext),
+            None => format!("{}.{}", file_stem(&item.original_name), ext),
+        };
+        let output_path = unique_path(destination, &file_name, &mut used_names);
+
+        let data = if write_metadata {
+            let mut xmp = sidecar::build_item_xmp(&conn, &item.id)?;
+            if edited {
+                xmp.crop = None;
+            }
+            if options.strip == MetadataStrip::Location {
+                xmp.latitude = None;
+                xmp.longitude = None;
+                xmp.place = None;
+            }
+
+            if embed::supports_embedding(&ext) {
+                embed::embed_metadata(&data, &ext, &xmp, item.has_gain_map && !rerender && !edited)?
+            } else {
+                let sidecar_path = output_path.with_file_name(format!("{}.xmp", output_path.file_name().and_then(|name| name.to_str()).unwrap_or_default()));
+                fs::write(sidecar_path, xmp.to_packet()).map_err(|e| utils::treat(e, "Unable to write the XMP sidecar"))?;
+                data
+            }
+        } else {
+            data
+        };
+
+        fs::write(&output_path, data).map_err(|e| utils::treat(e, "Unable to export item"))?;
+        exported.push(output_path.to_string_lossy().to_string());
+
+        let progress = ExportProgress { item_id: item.id, completed: index + 1, total: item_ids.len() };
+        app.emit("export-progress", progress).map_err(|e| utils::treat(e, "Unable to report export progress"))?;
+    }
+
+    Ok(exported)
+}
+
+pub fn decode_image(data: &Vec<u8>, ext: &str) -> Result<DynamicImage, String> {
+    let loaded = utils::load_image(data, ext)?;
+    let mut image = loaded.image;
+
+    if ["jpg", "jpeg"].contains(&ext) {
+        if let Ok(exif) = exif::Reader::new().read_from_container(&mut Cursor::new(data)) {
+            image = utils::apply_orientation(image, utils::exif_orientation(&exif));
+        }
+    }
+
+    Ok(color::prepare_for_display(image, loaded.icc_profile.as_deref(), loaded.hdr.transfer))
+}
+
+fn export_exif(original: &[u8], strip_location: bool) -> Result<Option<Vec<u8>>, String> {
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut Cursor::new(original)) else {
+        return Ok(None);
+    };
+
+    let fields: Vec<&exif::Field> = exif
+        .fields()
+        .filter(|field| field.ifd_num == exif::In::PRIMARY && !matches!(field.value, exif::Value::Unknown(..)))
+        .filter(|field| !(strip_location && field.tag.context() == exif::Context::Gps))
+        .filter(|field| !REWRITTEN_EXIF_TAGS.contains(&field.tag))
+        .collect();
+    if fields.is_empty() {
+        return Ok(None);
+    }
+
+    let mut writer = exif::experimental::Writer::new();
+    for field in fields {
+        writer.push_field(field);
+    }
+
+    let mut tiff = Cursor::new(Vec::new());
+    writer.write(&mut tiff, exif.little_endian()).map_err(|e| utils::treat(e, "Unable to write the EXIF metadata"))?;
+    Ok(Some(tiff.into_inner()))
+}
+
+pub fn fit_image(image: DynamicImage, max_size: Option<u32>) -> DynamicImage {
+    match max_size {
+        Some(max_size) if image.width() > max_size || image.height() > max_size => image.resize(max_size, max_size, FilterType::Lanczos3),
+        _ => image,
+    }
+}
+
+pub fn encode_image(image: &DynamicImage, format: ExportFormat, quality: u8) -> Result<Vec<u8>, String> {
+    let mut output = Vec::new();
+
+    match format {
+        ExportFormat::Jpeg => JpegEncoder::new_with_quality(&mut output, quality)
+            .encode_image(&image.to_rgb8())
+            .map_err(|e| utils::treat(e, "Unable to encode the exported image"))?,
+        ExportFormat::Png => image
+            .write_to(&mut Cursor::new(&mut output), ImageFormat::Png)
+            .map_err(|e| utils::treat(e, "Unable to encode the exported image"))?,
+        ExportFormat::Webp => {
+            let rgba = image.to_rgba8();
+            output = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height()).encode(quality as f32).to_vec();
+        }
+    }
+
+    Ok(output)
+}
+
+fn apply_template(conn: &Connection, template: &str, item: &utils::Item, seq: usize, total: usize) -> Result<String, String> {
+    let mut name = template
+        .replace("{name}", file_stem(&item.original_name))
+        .replace("{date}", &item.created_at.format("%Y-%m-%d").to_string())
+        .replace("{seq}", &format!("{:0width$}", seq, width = total.to_string().len()));
+
+    if name.contains("{album}") {
+        let album: Option<String> = conn
+            .query_row(
+                "SELECT album.name FROM album JOIN album_item ON album_item.album_id = album.id WHERE album_item.item_id = ?1 ORDER BY album.name LIMIT 1",
+                params![item.id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| utils::treat(e, "Unable to obtain the item's albums"))?;
+        name = name.replace("{album}", album.as_deref().unwrap_or(""));
+    }
+
+    let name = sanitize_file_name(&name);
+    Ok(if name.is_empty() { file_stem(&item.original_name).to_string() } else { name })
+}
+
+pub fn sanitize_file_name(name: &str) -> String {
+    let name: String = name.chars().map(|c| if "/\\:*?\"<>|".contains(c) || c.is_control() { '_' } else { c }).collect();
+    name.trim_matches(|c: char| c == '_' || c == '-' || c == '.' || c.is_whitespace()).to_string()
+}
+
+fn file_stem(file_name: &str) -> &str {
+    Path::new(file_name).file_stem().and_then(|stem| stem.to_str()).unwrap_or(file_name)
+}
+
+fn unique_path(destination: &Path, file_name: &str, used_names: &mut HashSet<String>) -> PathBuf {
+    let stem = file_stem(file_name);
+    let ext = utils::file_extension(file_name);
+    let mut candidate = file_name.to_string();
+    let mut counter = 1;
+
+    while used_names.contains(&candidate.to_lowercase()) || destination.join(&candidate).exists() {
+        candidate = if ext.is_empty() { format!("{} ({})", stem, counter) } else { format!("{} ({}).{}", stem, counter, ext) };
+        counter += 1;
+    }
+
+    used_names.insert(candidate.to_lowercase());
+    destination.join(candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiff(fields: &[exif::Field]) -> Vec<u8> {
+        let mut writer = exif::experimental::Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        tiff.into_inner()
+    }
+
+    fn field(tag: exif::Tag, value: exif::Value) -> exif::Field {
+        exif::Field { tag, ifd_num: exif::In::PRIMARY, value }
+    }
+
+    fn source_jpeg() -> Vec<u8> {
+        let fields = [
+            field(exif::Tag::Make, exif::Value::Ascii(vec![b"Fujifilm".to_vec()])),
+            field(exif::Tag::Orientation, exif::Value::Short(vec![6])),
+            field(exif::Tag::GPSLatitudeRef, exif::Value::Ascii(vec![b"N".to_vec()])),
+        ];
+        let image = DynamicImage::new_rgb8(4, 2);
+        embed::embed_exif(&encode_image(&image, ExportFormat::Jpeg, 90).unwrap(), "jpg", &tiff(&fields)).unwrap()
+    }
+
+    fn read(data: &[u8]) -> exif::Exif {
+        exif::Reader::new().read_from_container(&mut Cursor::new(data)).unwrap()
+    }
+
+    #[test]
+    fn reencoded_exports_keep_exif() {
+        let exported = export_exif(&source_jpeg(), false).unwrap().unwrap();
+        let exif = exif::Reader::new().read_raw(exported).unwrap();
+
+        assert!(exif.get_field(exif::Tag::Make, exif::In::PRIMARY).is_some());
+        assert!(exif.get_field(exif::Tag::GPSLatitudeRef, exif::In::PRIMARY).is_some());
+        assert!(exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY).is_none());
+    }
+
+    #[test]
+    fn location_strip_only_removes_gps() {
+        let tiff = export_exif(&source_jpeg(), true).unwrap().unwrap();
+        let image = DynamicImage::new_rgba8(4, 2);
+
+        for format in [ExportFormat::Jpeg, ExportFormat::Png, ExportFormat::Webp] {
+            let data = embed::embed_exif(&encode_image(&image, format, 90).unwrap(), format.extension(), &tiff).unwrap();
+            let exif = read(&data);
+
+            assert!(exif.get_field(exif::Tag::Make, exif::In::PRIMARY).is_some());
+            assert!(exif.get_field(exif::Tag::GPSLatitudeRef, exif::In::PRIMARY).is_none());
+            assert!(utils::load_image(&data, format.extension()).is_ok());
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,198 @@
+use quick_xml::escape::escape;
+use rusqlite::params;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use tauri::Emitter;
+
+use crate::modules::album;
+use crate::modules::edits;
+use crate::modules::export::{self, ExportFormat, ExportProgress};
+use crate::modules::library;
+use crate::modules::utils;
+
+const STYLESHEET: &str = r#"* {
+    box-sizing: border-box;
+}
+
+body {
+    margin: 0;
+    padding: 2rem;
+    background: #111;
+    color: #eee;
+    font-family: system-ui, -apple-system, "Segoe UI", sans-serif;
+}
+
+header {
+    margin-bottom: 2rem;
+}
+
+h1 {
+    margin: 0 0 0.5rem;
+    font-weight: 600;
+}
+
+header p {
+    margin: 0;
+    color: #aaa;
+}
+
+.grid {
+    display: grid;
+    grid-template-columns: repeat(auto-fill, minmax(220px, 1fr));
+    gap: 1rem;
+}
+
+figure {
+    margin: 0;
+}
+
+figure img {
+    display: block;
+    width: 100%;
+    aspect-ratio: 1;
+    object-fit: cover;
+    border-radius: 6px;
+}
+
+figcaption {
+    margin-top: 0.5rem;
+    font-size: 0.875rem;
+    color: #ccc;
+}
+
+figcaption strong {
+    display: block;
+    color: #fff;
+}
+
+.lightbox {
+    display: none;
+    position: fixed;
+    inset: 0;
+    padding: 2rem;
+    background: rgba(0, 0, 0, 0.92);
+    flex-direction: column;
+    align-items: center;
+    justify-content: center;
+}
+
+.lightbox:target {
+    display: flex;
+}
+
+.lightbox img {
+    max-width: 100%;
+    max-height: calc(100% - 4rem);
+    object-fit: contain;
+}
+
+.lightbox p {
+    margin: 1rem 0 0;
+    text-align: center;
+}
+
+.lightbox .close {
+    position: absolute;
+    inset: 0;
+}
+"#;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct GalleryOptions {
+    pub image_size: u32,
+    pub thumbnail_size: u32,
+    pub quality: u8,
+}
+
+impl Default for GalleryOptions {
+    fn default() -> Self {
+        GalleryOptions { image_size: 2048, thumbnail_size: 512, quality: 85 }
+    }
+}
+
+#[tauri::command]
+pub async fn export_album_gallery(app: tauri::AppHandle, library_id: String, album_id: String, destination: String, options: Option<GalleryOptions>) -> Result<String, String> {
+    let options = options.unwrap_or_default();
+    let quality = options.quality.clamp(1, 100);
+    let library_root = library::get_library_root_path(&app, &library_id)?;
+    let conn = library::get_db_connection(&app, &library_id)?;
+
+    let album = conn
+        .query_row("SELECT * FROM album WHERE id = ?1", params![album_id], album::deserialize_album)
+        .map_err(|e| utils::treat(e, "Album not found"))?;
+
+    let mut stmt = conn
+        .prepare("SELECT item.* FROM item JOIN album_item ON album_item.item_id = item.id WHERE album_item.album_id = ?1 ORDER BY item.created_at")
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+    let item_iter = stmt.query_map(params![album_id], utils::deserialize_item).map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+
+    let mut items = Vec::new();
+    for item in item_iter {
+        items.push(item.map_err(|e| utils::treat(e, "Unable to obtain items"))?);
+    }
+
+    let folder_name = export::sanitize_file_name(&album.name);
+    let gallery_dir = Path::new(&destination).join(if folder_name.is_empty() { album.id.clone() } else { folder_name });
+    let images_dir = gallery_dir.join("images");
+    let thumbs_dir = gallery_dir.join("thumbnails");
+    fs::create_dir_all(&images_dir).map_err(|e| utils::treat(e, "Unable to create the gallery directory"))?;
+    fs::create_dir_all(&thumbs_dir).map_err(|e| utils::treat(e, "Unable to create the gallery directory"))?;
+
+    let mut figures = Vec::new();
+    let mut lightboxes = Vec::new();
+
+    for (index, item) in items.iter().enumerate() {
+        let file_name = format!("{:04}.jpg", index + 1);
+        let image = export::fit_image(edits::render_item(&library_root, item)?, Some(options.image_size));
+        let thumbnail = export::fit_image(image.clone(), Some(options.thumbnail_size));
+        fs::write(images_dir.join(&file_name), export::encode_image(&image, ExportFormat::Jpeg, quality)?).map_err(|e| utils::treat(e, "Unable to write the gallery image"))?;
+        fs::write(thumbs_dir.join(&file_name), export::encode_image(&thumbnail, ExportFormat::Jpeg, quality)?).map_err(|e| utils::treat(e, "Unable to write the gallery thumbnail"))?;
+
+        let title = item.title.as_deref().map(|title| escape(title).to_string());
+        let caption = item.caption.as_deref().map(|caption| escape(caption).to_string());
+        let alt = title.clone().or_else(|| caption.clone()).unwrap_or_else(|| escape(&item.original_name).to_string());
+        let mut figcaption = String::new();
+        if let Some(title) = &title {
+            figcaption.push_str(&format!("<strong>{}</strong>", title));
+        }
+        if let Some(caption) = &caption {
+            figcaption.push_str(caption);
+        }
+
+        figures.push(format!(
+            "            <figure>\n                <a href=\"#photo-{id}\"><img src=\"thumbnails/{file}\" alt=\"{alt}\" loading=\"lazy\"></a>\n{caption}            </figure>\n",
+            id = index + 1,
+            file = file_name,
+            alt = alt,
+            caption = if figcaption.is_empty() { String::new() } else { format!("                <figcaption>{}</figcaption>\n", figcaption) },
+        ));
+        lightboxes.push(format!(
+            "        <div class=\"lightbox\" id=\"photo-{id}\">\n            <a class=\"close\" href=\"#\" aria-label=\"Close\"></a>\n            <img src=\"images/{file}\" alt=\"{alt}\">\n{caption}        </div>\n",
+            id = index + 1,
+            file = file_name,
+            alt = alt,
+            caption = if figcaption.is_empty() { String::new() } else { format!("            <p>{}</p>\n", figcaption) },
+        ));
+
+        let progress = ExportProgress { item_id: item.id.clone(), completed: index + 1, total: items.len() };
+        app.emit("export-progress", progress).map_err(|e| utils::treat(e, "Unable to report export progress"))?;
+    }
+
+    let name = escape(&album.name);
+    let description = album.description.as_deref().map_or(String::new(), |description| format!("            <p>{}</p>\n", escape(description)));
+    let index = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n    <head>\n        <meta charset=\"utf-8\">\n        <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n        <title>{name}</title>\n        <link rel=\"stylesheet\" href=\"style.css\">\n    </head>\n    <body>\n        <header>\n            <h1>{name}</h1>\n{description}        </header>\n        <main class=\"grid\">\n{figures}        </main>\n{lightboxes}    </body>\n</html>\n",
+        name = name,
+        description = description,
+        figures = figures.concat(),
+        lightboxes = lightboxes.concat(),
+    );
+
+    fs::write(gallery_dir.join("style.css"), STYLESHEET).map_err(|e| utils::treat(e, "Unable to write the gallery stylesheet"))?;
+    let index_path = gallery_dir.join("index.html");
+    fs::write(&index_path, index).map_err(|e| utils::treat(e, "Unable to write the gallery index"))?;
+
+    Ok(index_path.to_string_lossy().to_string())
+}
\ No newline at end of file
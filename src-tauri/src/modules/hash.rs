@@ -0,0 +1,277 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use tauri::Emitter;
+
+use crate::modules::library;
+use crate::modules::utils;
+
+pub const CURRENT_ALGORITHM: ChecksumAlgorithm = ChecksumAlgorithm::Blake3;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+static REHASHING_LIBRARIES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    #[default]
+    Md5,
+    Blake3,
+}
+
+pub enum Hasher {
+    Md5(md5::Context),
+    Blake3(Box<blake3::Hasher>),
+}
+
+#[derive(Clone, Serialize)]
+pub struct RehashProgress {
+    pub item_id: String,
+    pub completed: usize,
+    pub total: usize,
+    pub mismatch: bool,
+}
+
+impl ChecksumAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Md5 => "md5",
+            ChecksumAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    pub fn from_name(name: &str) -> ChecksumAlgorithm {
+        match name {
+            "blake3" => ChecksumAlgorithm::Blake3,
+            _ => ChecksumAlgorithm::Md5,
+        }
+    }
+}
+
+impl Hasher {
+    pub fn new(algorithm: ChecksumAlgorithm) -> Hasher {
+        match algorithm {
+            ChecksumAlgorithm::Md5 => Hasher::Md5(md5::Context::new()),
+            ChecksumAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(context) => context.consume(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    pub fn finalize(self) -> String {
+        match self {
+            Hasher::Md5(context) => format!("{:x}", context.compute()),
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+pub fn hash_bytes(data: &[u8], algorithm: ChecksumAlgorithm) -> String {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+pub fn hash_file(path: &Path, algorithm: ChecksumAlgorithm) -> Result<String, String> {
+    Ok(hash_file_with(path, &[algorithm])?.remove(0))
+}
+
+pub fn hash_file_with(path: &Path, algorithms: &[ChecksumAlgorithm]) -> Result<Vec<String>, String> {
+    let file = File::open(path).map_err(|e| utils::treat(e, "Unable to read photo data"))?;
+    let mut reader = BufReader::new(file);
+    let mut hashers: Vec<Hasher> = algorithms.iter().map(|algorithm| Hasher::new(*algorithm)).collect();
+    let mut buffer = vec![0; BUFFER_SIZE];
+
+    loop {
+        let read = reader.read(&mut buffer).map_err(|e| utils::treat(e, "Unable to read photo data"))?;
+        if read == 0 {
+            break;
+        }
+        for hasher in &mut hashers {
+            hasher.update(&buffer[..read]);
+        }
+    }
+
+    Ok(hashers.into_iter().map(Hasher::finalize).collect())
+}
+
+pub fn copy_file(source: &Path, dest: &Path, algorithm: ChecksumAlgorithm) -> Result<(String, u64), String> {
+    let copied = File::create(dest).map_err(|e| utils::treat(e, "Unable to copy item")).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        let mut size = 0;
+        let checksum = stream_file(source, algorithm, |chunk| {
+            size += chunk.len() as u64;
+            writer.write_all(chunk).map_err(|e| utils::treat(e, "Unable to copy item"))
+        })?;
+        let file = writer.into_inner().map_err(|e| utils::treat(e, "Unable to copy item"))?;
+        file.sync_all().map_err(|e| utils::treat(e, "Unable to copy item"))?;
+        Ok((checksum, size))
+    });
+    if copied.is_err() {
+        let _ = fs::remove_file(dest);
+    }
+    copied
+}
+
+fn stream_file(source: &Path, algorithm: ChecksumAlgorithm, mut sink: impl FnMut(&[u8]) -> Result<(), String>) -> Result<String, String> {
+    let mut reader = BufReader::new(File::open(source).map_err(|e| utils::treat(e, "Unable to read photo data"))?);
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0; BUFFER_SIZE];
+
+    loop {
+        let read = reader.read(&mut buffer).map_err(|e| utils::treat(e, "Unable to read photo data"))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        sink(&buffer[..read])?;
+    }
+
+    Ok(hasher.finalize())
+}
+
+pub fn find_duplicate(conn: &Connection, path: &Path) -> Result<Option<String>, String> {
+    let mut stmt = conn.prepare("SELECT DISTINCT checksum_algorithm FROM item").map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+    let algorithms = stmt
+        .query_map([], |row| row.get::<_, Option<String>>(0))
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))?
+        .into_iter()
+        .map(|name| ChecksumAlgorithm::from_name(name.as_deref().unwrap_or_default()))
+        .collect::<Vec<_>>();
+    if algorithms.is_empty() {
+        return Ok(None);
+    }
+
+    for (algorithm, checksum) in algorithms.iter().zip(hash_file_with(path, &algorithms)?) {
+        let existing = find_by_checksum(conn, &checksum, *algorithm)?;
+        if existing.is_some() {
+            return Ok(existing);
+        }
+    }
+
+    Ok(None)
+}
+
+pub fn find_by_checksum(conn: &Connection, checksum: &str, algorithm: ChecksumAlgorithm) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT id FROM item WHERE checksum = ?1 AND checksum_algorithm = ?2",
+        params![checksum, algorithm.name()],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| utils::treat(e, "Unable to obtain items"))
+}
+
+#[tauri::command]
+pub fn rehash_library(app: tauri::AppHandle, library_id: String) -> Result<(), String> {
+    let library_root = library::get_library_root_path(&app, &library_id)?;
+    let conn = library::get_db_connection(&app, &library_id)?;
+    start_rehash(app, library_id, library_root, conn);
+    Ok(())
+}
+
+pub fn needs_rehash(conn: &Connection) -> Result<bool, String> {
+    conn.query_row("SELECT EXISTS (SELECT 1 FROM item WHERE checksum_algorithm IS NOT ?1)", params![CURRENT_ALGORITHM.name()], |row| row.get(0))
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))
+}
+
+pub fn start_rehash(app: tauri::AppHandle, library_id: String, library_root: PathBuf, conn: Connection) {
+    {
+        let mut rehashing = REHASHING_LIBRARIES.lock().unwrap_or_else(PoisonError::into_inner);
+        if rehashing.contains(&library_id) {
+            return;
+        }
+        rehashing.push(library_id.clone());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(e) = rehash_items(&app, &conn, &library_root) {
+            log::error!("Unable to rehash the library: {}", e);
+        }
+        REHASHING_LIBRARIES.lock().unwrap_or_else(PoisonError::into_inner).retain(|id| *id != library_id);
+    });
+}
+
+fn rehash_items(app: &tauri::AppHandle, conn: &Connection, library_root: &Path) -> Result<(), String> {
+    let mut stmt = conn.prepare("SELECT * FROM item WHERE checksum_algorithm IS NOT ?1").map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+    let items = stmt
+        .query_map(params![CURRENT_ALGORITHM.name()], utils::deserialize_item)
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+
+    for (index, item) in items.iter().enumerate() {
+        let original_path = utils::original_path(library_root, item);
+        let mut mismatch = false;
+
+        if original_path.is_file() {
+            let checksums = hash_file_with(&original_path, &[item.checksum_algorithm, CURRENT_ALGORITHM])?;
+            mismatch = checksums[0] != item.checksum;
+
+            if !mismatch {
+                conn.execute(
+                    "UPDATE item SET checksum = ?1, checksum_algorithm = ?2 WHERE id = ?3 AND checksum = ?4",
+                    params![checksums[1], CURRENT_ALGORITHM.name(), item.id, item.checksum],
+                ).map_err(|e| utils::treat(e, "Unable to update the item checksum"))?;
+            }
+        }
+
+        let progress = RehashProgress { item_id: item.id.clone(), completed: index + 1, total: items.len(), mismatch };
+        app.emit("rehash-progress", progress).map_err(|e| utils::treat(e, "Unable to report rehash progress"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::schema;
+
+    #[test]
+    fn legacy_checksums_need_rehashing() {
+        let conn = schema::base_library();
+        schema::migrate(&conn).unwrap();
+        assert!(!needs_rehash(&conn).unwrap());
+
+        conn.execute(
+            "INSERT INTO item (id, original_name, file_type, file_size, width, height, checksum, created_at, checksum_algorithm) VALUES (?1, 'a.jpg', 'image/jpeg', 1, 1, 1, ?2, '', ?3)",
+            params!["a", hash_bytes(b"a", CURRENT_ALGORITHM), CURRENT_ALGORITHM.name()],
+        ).unwrap();
+        assert!(!needs_rehash(&conn).unwrap());
+
+        conn.execute("UPDATE item SET checksum_algorithm = ?1", params![ChecksumAlgorithm::Md5.name()]).unwrap();
+        assert!(needs_rehash(&conn).unwrap());
+    }
+    #[test]
+    fn copies_are_hashed_while_streaming() {
+        let root = std::env::temp_dir().join(format!("hash-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let source = root.join("source.jpg");
+        let dest = root.join("dest.jpg");
+        fs::write(&source, vec![7; BUFFER_SIZE * 2 + 3]).unwrap();
+
+        let (checksum, size) = copy_file(&source, &dest, CURRENT_ALGORITHM).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), fs::read(&source).unwrap());
+        assert_eq!(checksum, hash_file(&source, CURRENT_ALGORITHM).unwrap());
+        assert_eq!(size, BUFFER_SIZE as u64 * 2 + 3);
+
+        assert!(copy_file(&root.join("missing.jpg"), &dest, CURRENT_ALGORITHM).is_err());
+        assert!(!dest.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,215 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tauri::Emitter;
+
+use crate::modules::edits;
+use crate::modules::hash;
+use crate::modules::library::{self, ImportMode, PreparedItem};
+use crate::modules::sidecar;
+use crate::modules::staging::ImportStage;
+use crate::modules::utils;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IntegrityIssue {
+    MissingOriginal { item_id: String },
+    Offline { item_id: String },
+    ChecksumMismatch { item_id: String },
+    MissingThumbnail { item_id: String },
+    OrphanFile { file: String },
+}
+
+type StagedOrphan = (String, PreparedItem);
+
+#[derive(Debug, Serialize)]
+pub struct IntegrityReport {
+    pub checked: usize,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RepairReport {
+    pub reimported: Vec<utils::Item>,
+    pub thumbnails: Vec<String>,
+    pub quarantined: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct VerifyProgress {
+    pub item_id: String,
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[tauri::command]
+pub async fn verify_library(app: tauri::AppHandle, library_id: String) -> Result<IntegrityReport, String> {
+    let library_root = library::get_library_root_path(&app, &library_id)?;
+    let conn = library::get_db_connection(&app, &library_id)?;
+    scan(&app, &conn, &library_root)
+}
+
+#[tauri::command]
+pub async fn repair_library(app: tauri::AppHandle, library_id: String) -> Result<RepairReport, String> {
+    let library_root = library::get_library_root_path(&app, &library_id)?;
+    let originals_dir = library_root.join("originals");
+    let quarantine_dir = library_root.join("quarantine");
+    for dir in [library_root.join("thumbnails"), library_root.join("previews"), quarantine_dir.clone()] {
+        fs::create_dir_all(dir).map_err(|e| utils::treat(e, "Unable to create required directory"))?;
+    }
+
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let report = scan(&app, &conn, &library_root)?;
+    let mut repaired = RepairReport::default();
+    let mut broken = HashSet::new();
+    let mut orphans = Vec::new();
+
+    for issue in report.issues {
+        match issue {
+            IntegrityIssue::MissingOriginal { item_id } | IntegrityIssue::Offline { item_id } => {
+                broken.insert(item_id);
+            }
+            IntegrityIssue::ChecksumMismatch { item_id } => {
+                let item = get_item(&conn, &item_id)?;
+                if item.source_path.is_some() {
+                    broken.insert(item_id);
+                    continue;
+                }
+
+                let file = utils::original_file_name(&item);
+                quarantine(&originals_dir.join(&file), &quarantine_dir)?;
+                edits::clear_render_cache(&library_root, &item_id)?;
+                repaired.quarantined.push(file);
+                broken.insert(item_id);
+            }
+            IntegrityIssue::MissingThumbnail { item_id } => {
+                if broken.contains(&item_id) {
+                    continue;
+                }
+
+                let item = get_item(&conn, &item_id)?;
+                edits::render_previews(&library_root, &item)?;
+                repaired.thumbnails.push(item_id);
+            }
+            IntegrityIssue::OrphanFile { file } => orphans.push(file),
+        }
+    }
+
+    let stage = ImportStage::begin(&conn, &library_root)?;
+    let (reimported, rejected) = match stage_orphans(&conn, &originals_dir, orphans, &stage) {
+        Ok(staged) => staged,
+        Err(e) => {
+            stage.abort(&conn)?;
+            return Err(e);
+        }
+    };
+    let (files, prepared): (Vec<String>, Vec<PreparedItem>) = reimported.into_iter().unzip();
+    stage.commit(&mut conn, |tx| library::insert_items(tx, &prepared))?;
+
+    for file in &files {
+        let stale_sidecar = originals_dir.join(file).with_extension("xmp");
+        if stale_sidecar.exists() {
+            fs::remove_file(&stale_sidecar).map_err(|e| utils::treat(e, "Unable to remove the XMP sidecar"))?;
+        }
+    }
+    for file in rejected {
+        quarantine(&originals_dir.join(&file), &quarantine_dir)?;
+        repaired.quarantined.push(file);
+    }
+
+    repaired.reimported = prepared.into_iter().map(|prepared| prepared.item).collect();
+    let item_ids: Vec<String> = repaired.reimported.iter().map(|item| item.id.clone()).collect();
+    if let Err(e) = sidecar::sync_items(&app, &library_id, &item_ids) {
+        log::warn!("Unable to write the sidecars of imported items: {}", e);
+    }
+
+    Ok(repaired)
+}
+
+fn stage_orphans(conn: &Connection, originals_dir: &Path, orphans: Vec<String>, stage: &ImportStage) -> Result<(Vec<StagedOrphan>, Vec<String>), String> {
+    let mut prepared = Vec::new();
+    let mut rejected = Vec::new();
+
+    for file in orphans {
+        let path = originals_dir.join(&file);
+        let reimported = match hash::find_duplicate(conn, &path)? {
+            Some(_) => None,
+            None => library::prepare_item(&path.to_string_lossy(), stage, ImportMode::Move).ok(),
+        };
+
+        match reimported {
+            Some(item) => prepared.push((file, item)),
+            None => rejected.push(file),
+        }
+    }
+
+    Ok((prepared, rejected))
+}
+
+fn scan(app: &tauri::AppHandle, conn: &Connection, library_root: &Path) -> Result<IntegrityReport, String> {
+    let originals_dir = library_root.join("originals");
+    let thumbs_dir = library_root.join("thumbnails");
+
+    let mut stmt = conn.prepare("SELECT * FROM item").map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+    let items = stmt
+        .query_map([], utils::deserialize_item)
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+
+    let mut issues = Vec::new();
+    let mut known_files = HashSet::new();
+    let mut report_issue = |issue: IntegrityIssue| -> Result<(), String> {
+        app.emit("verify-issue", issue.clone()).map_err(|e| utils::treat(e, "Unable to report verification progress"))?;
+        issues.push(issue);
+        Ok(())
+    };
+
+    for (index, item) in items.iter().enumerate() {
+        let original_path = utils::original_path(library_root, item);
+        known_files.insert(utils::original_file_name(item));
+
+        if !original_path.is_file() && item.source_path.is_some() {
+            report_issue(IntegrityIssue::Offline { item_id: item.id.clone() })?;
+        } else if !original_path.is_file() {
+            report_issue(IntegrityIssue::MissingOriginal { item_id: item.id.clone() })?;
+        } else if hash::hash_file(&original_path, item.checksum_algorithm)? != item.checksum {
+            report_issue(IntegrityIssue::ChecksumMismatch { item_id: item.id.clone() })?;
+        }
+
+        if !thumbs_dir.join(format!("{}.webp", item.id)).is_file() {
+            report_issue(IntegrityIssue::MissingThumbnail { item_id: item.id.clone() })?;
+        }
+
+        let progress = VerifyProgress { item_id: item.id.clone(), completed: index + 1, total: items.len() };
+        app.emit("verify-progress", progress).map_err(|e| utils::treat(e, "Unable to report verification progress"))?;
+    }
+
+    if let Ok(entries) = fs::read_dir(&originals_dir) {
+        for entry in entries.flatten() {
+            let file = entry.file_name().to_string_lossy().to_string();
+            if !entry.path().is_file() || utils::file_extension(&file).eq_ignore_ascii_case("xmp") || known_files.contains(&file) {
+                continue;
+            }
+
+            report_issue(IntegrityIssue::OrphanFile { file })?;
+        }
+    }
+
+    Ok(IntegrityReport { checked: items.len(), issues })
+}
+
+fn get_item(conn: &Connection, item_id: &str) -> Result<utils::Item, String> {
+    conn.query_row("SELECT * FROM item WHERE id = ?1", params![item_id], utils::deserialize_item)
+        .map_err(|e| utils::treat(e, "Item not found"))
+}
+
+fn quarantine(path: &Path, quarantine_dir: &Path) -> Result<(), String> {
+    let Some(file_name) = path.file_name() else {
+        return Ok(());
+    };
+
+    fs::rename(path, quarantine_dir.join(file_name)).map_err(|e| utils::treat(e, "Unable to quarantine the file"))
+}
\ No newline at end of file
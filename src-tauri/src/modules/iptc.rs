@@ -0,0 +1,247 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+pub const PHOTOSHOP_HEADER: &[u8] = b"Photoshop 3.0\0";
+const IPTC_RESOURCE: u16 = 0x0404;
+
+#[derive(Debug, Default)]
+pub struct IptcData {
+    pub title: Option<String>,
+    pub caption: Option<String>,
+    pub keywords: Vec<String>,
+}
+
+impl IptcData {
+    pub fn from_jpeg(data: &[u8]) -> Option<IptcData> {
+        let resources = find_photoshop_segment(data)?;
+        let iptc = find_resource(resources, IPTC_RESOURCE)?;
+        let mut result = IptcData::default();
+
+        for (record, dataset, value) in read_datasets(iptc) {
+            match (record, dataset) {
+                (2, 5) => result.title = decode(value),
+                (2, 120) => result.caption = decode(value),
+                (2, 25) => result.keywords.extend(decode(value)),
+                _ => {}
+            }
+        }
+
+        Some(result)
+    }
+
+    pub fn from_jpeg_file(path: &Path) -> Option<IptcData> {
+        IptcData::from_jpeg(&read_jpeg_header(path)?)
+    }
+
+    pub fn to_photoshop_segment(&self, jpeg: &[u8]) -> Vec<u8> {
+        let mut datasets = Vec::new();
+        write_dataset(&mut datasets, 1, 90, &[0x1B, 0x25, 0x47]);
+        write_dataset(&mut datasets, 2, 0, &[0x00, 0x04]);
+        if let Some(title) = &self.title {
+            write_dataset(&mut datasets, 2, 5, title.as_bytes());
+        }
+        for keyword in &self.keywords {
+            write_dataset(&mut datasets, 2, 25, keyword.as_bytes());
+        }
+        if let Some(caption) = &self.caption {
+            write_dataset(&mut datasets, 2, 120, caption.as_bytes());
+        }
+
+        let mut segment = PHOTOSHOP_HEADER.to_vec();
+        for (resource_id, resource) in find_photoshop_segment(jpeg).map(read_resources).unwrap_or_default() {
+            if resource_id != IPTC_RESOURCE {
+                segment.extend_from_slice(resource);
+            }
+        }
+
+        segment.extend_from_slice(b"8BIM");
+        segment.extend_from_slice(&IPTC_RESOURCE.to_be_bytes());
+        segment.extend_from_slice(&[0x00, 0x00]);
+        segment.extend_from_slice(&(datasets.len() as u32).to_be_bytes());
+        segment.extend_from_slice(&datasets);
+        if datasets.len() % 2 == 1 {
+            segment.push(0x00);
+        }
+
+        segment
+    }
+}
+
+fn write_dataset(output: &mut Vec<u8>, record: u8, dataset: u8, value: &[u8]) {
+    let mut length = value.len().min(0x7FFF);
+    while length > 0 && std::str::from_utf8(&value[..length]).is_err() {
+        length -= 1;
+    }
+
+    output.extend_from_slice(&[0x1C, record, dataset]);
+    output.extend_from_slice(&(length as u16).to_be_bytes());
+    output.extend_from_slice(&value[..length]);
+}
+
+fn read_jpeg_header(path: &Path) -> Option<Vec<u8>> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let mut header = vec![0; 2];
+    reader.read_exact(&mut header).ok()?;
+    if header != [0xFF, 0xD8] {
+        return None;
+    }
+
+    loop {
+        let mut marker = [0; 4];
+        if reader.read_exact(&mut marker).is_err() || marker[0] != 0xFF {
+            return Some(header);
+        }
+        header.extend_from_slice(&marker);
+        if marker[1] == 0xDA || marker[1] == 0xD9 {
+            return Some(header);
+        }
+
+        let length = (u16::from_be_bytes([marker[2], marker[3]]) as usize).checked_sub(2)?;
+        let start = header.len();
+        header.resize(start + length, 0);
+        reader.read_exact(&mut header[start..]).ok()?;
+    }
+}
+
+fn find_photoshop_segment(data: &[u8]) -> Option<&[u8]> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut offset = 2;
+    while offset + 4 <= data.len() && data[offset] == 0xFF {
+        let marker = data[offset + 1];
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+
+        let length = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        let segment = data.get(offset + 4..offset + 2 + length)?;
+        if marker == 0xED && segment.starts_with(PHOTOSHOP_HEADER) {
+            return Some(&segment[PHOTOSHOP_HEADER.len()..]);
+        }
+
+        offset += 2 + length;
+    }
+
+    None
+}
+
+fn find_resource(data: &[u8], id: u16) -> Option<&[u8]> {
+    read_resources(data).into_iter().find(|(resource_id, _)| *resource_id == id).and_then(|(_, resource)| {
+        let name_size = (resource[6] as usize + 2) & !1;
+        let size = u32::from_be_bytes(resource[6 + name_size..10 + name_size].try_into().ok()?) as usize;
+        resource.get(10 + name_size..10 + name_size + size)
+    })
+}
+
+fn read_resources(data: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut resources = Vec::new();
+    let mut offset = 0;
+
+    while offset + 12 <= data.len() && &data[offset..offset + 4] == b"8BIM" {
+        let resource_id = u16::from_be_bytes([data[offset + 4], data[offset + 5]]);
+        let name_size = (data[offset + 6] as usize + 2) & !1;
+        let size_offset = offset + 6 + name_size;
+        let Some(size) = data.get(size_offset..size_offset + 4).map(|size| u32::from_be_bytes(size.try_into().unwrap()) as usize) else {
+            break;
+        };
+        if size_offset + 4 + size > data.len() {
+            break;
+        }
+
+        let end = (size_offset + 4 + ((size + 1) & !1)).min(data.len());
+        resources.push((resource_id, &data[offset..end]));
+        offset = end;
+    }
+
+    resources
+}
+
+fn read_datasets(data: &[u8]) -> Vec<(u8, u8, &[u8])> {
+    let mut datasets = Vec::new();
+    let mut offset = 0;
+
+    while offset + 5 <= data.len() && data[offset] == 0x1C {
+        let record = data[offset + 1];
+        let dataset = data[offset + 2];
+        let size = u16::from_be_bytes([data[offset + 3], data[offset + 4]]) as usize;
+        if size & 0x8000 != 0 {
+            break;
+        }
+
+        let Some(value) = data.get(offset + 5..offset + 5 + size) else {
+            break;
+        };
+        datasets.push((record, dataset, value));
+        offset += 5 + size;
+    }
+
+    datasets
+}
+
+fn decode(value: &[u8]) -> Option<String> {
+    let text = match std::str::from_utf8(value) {
+        Ok(text) => text.to_string(),
+        Err(_) => value.iter().map(|&byte| byte as char).collect(),
+    };
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string();
+
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg_with_resources(resources: &[u8]) -> Vec<u8> {
+        let body = [PHOTOSHOP_HEADER, resources].concat();
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xED];
+        jpeg.extend_from_slice(&((body.len() + 2) as u16).to_be_bytes());
+        jpeg.extend_from_slice(&body);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+        jpeg
+    }
+
+    fn resource(id: u16, body: &[u8]) -> Vec<u8> {
+        let mut resource = b"8BIM".to_vec();
+        resource.extend_from_slice(&id.to_be_bytes());
+        resource.extend_from_slice(&[0, 0]);
+        resource.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        resource.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            resource.push(0);
+        }
+        resource
+    }
+
+    #[test]
+    fn datasets_round_trip() {
+        let iptc = IptcData { title: Some("Harbour".to_string()), caption: Some("Evening".to_string()), keywords: vec!["Boats".to_string(), "Lisbon".to_string()] };
+        let segment = iptc.to_photoshop_segment(&[]);
+        let parsed = IptcData::from_jpeg(&jpeg_with_resources(&segment[PHOTOSHOP_HEADER.len()..])).unwrap();
+
+        assert_eq!(parsed.title.as_deref(), Some("Harbour"));
+        assert_eq!(parsed.caption.as_deref(), Some("Evening"));
+        assert_eq!(parsed.keywords, vec!["Boats", "Lisbon"]);
+    }
+
+    #[test]
+    fn only_the_iptc_resource_is_replaced() {
+        let thumbnail = resource(0x0409, b"thumb");
+        let old = IptcData { title: Some("Old".to_string()), ..Default::default() }.to_photoshop_segment(&[]);
+        let jpeg = jpeg_with_resources(&[&old[PHOTOSHOP_HEADER.len()..], &thumbnail].concat());
+
+        let segment = IptcData { title: Some("New".to_string()), ..Default::default() }.to_photoshop_segment(&jpeg);
+        let resources = read_resources(&segment[PHOTOSHOP_HEADER.len()..]);
+
+        assert_eq!(resources.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![0x0409, IPTC_RESOURCE]);
+        assert_eq!(resources[0].1, thumbnail.as_slice());
+        assert_eq!(IptcData::from_jpeg(&jpeg_with_resources(&segment[PHOTOSHOP_HEADER.len()..])).unwrap().title.as_deref(), Some("New"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,500 @@
+use chrono::{DateTime, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value as JsonValue};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::modules::edits;
+use crate::modules::library;
+use crate::modules::sidecar;
+use crate::modules::utils;
+
+const MAX_HISTORY: i64 = 200;
+
+type Row = Map<String, JsonValue>;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Change {
+    Insert { table: String, row: Row },
+    Delete { table: String, row: Row },
+    Update { table: String, before: Row, after: Row },
+    Move { from: String, to: String },
+}
+
+#[derive(Debug, Serialize)]
+pub struct Operation {
+    pub id: i64,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub undone: bool,
+    pub change_count: usize,
+}
+
+pub struct Snapshot {
+    table: &'static str,
+    column: &'static str,
+    ids: Vec<String>,
+    rows: Vec<Row>,
+}
+
+pub fn capture(tx: &Transaction, table: &'static str, column: &'static str, ids: &[String]) -> Result<Snapshot, String> {
+    let rows = read_rows(tx, table, column, ids)?;
+    Ok(Snapshot { table, column, ids: ids.to_vec(), rows })
+}
+
+pub fn record(tx: &Transaction, name: &str, snapshots: Vec<Snapshot>) -> Result<(), String> {
+    record_with_moves(tx, name, snapshots, Vec::new())
+}
+
+pub fn record_with_moves(tx: &Transaction, name: &str, snapshots: Vec<Snapshot>, moves: Vec<(String, String)>) -> Result<(), String> {
+    let mut changes = Vec::new();
+
+    for snapshot in snapshots {
+        let after = read_rows(tx, snapshot.table, snapshot.column, &snapshot.ids)?;
+        let keys = key_columns(snapshot.table);
+        let mut after_by_key: HashMap<String, Row> = after.into_iter().map(|row| (row_key(&row, keys), row)).collect();
+
+        for before in snapshot.rows {
+            match after_by_key.remove(&row_key(&before, keys)) {
+                Some(after) if after != before => changes.push(Change::Update { table: snapshot.table.to_string(), before, after }),
+                Some(_) => {}
+                None => changes.push(Change::Delete { table: snapshot.table.to_string(), row: before }),
+            }
+        }
+        for row in after_by_key.into_values() {
+            changes.push(Change::Insert { table: snapshot.table.to_string(), row });
+        }
+    }
+
+    changes.extend(moves.into_iter().map(|(from, to)| Change::Move { from, to }));
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let changes_json = serde_json::to_string(&changes).map_err(|e| utils::treat(e, "Unable to record the operation"))?;
+    let library_root = tx.path().and_then(|path| Path::new(path).parent());
+    if let Some(library_root) = library_root {
+        discard(tx, library_root, "undone = 1", [])?;
+    }
+    tx.execute("DELETE FROM operation_log WHERE undone = 1", []).map_err(|e| utils::treat(e, "Unable to record the operation"))?;
+    tx.execute(
+        "INSERT INTO operation_log (name, changes, created_at) VALUES (?1, ?2, ?3)",
+        params![name, changes_json, Utc::now().to_rfc3339()],
+    ).map_err(|e| utils::treat(e, "Unable to record the operation"))?;
+
+    match library_root {
+        Some(library_root) => discard(tx, library_root, "id <= (SELECT max(id) FROM operation_log) - ?1", [MAX_HISTORY]),
+        None => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub fn get_history(app: tauri::AppHandle, library_id: String, limit: Option<u32>) -> Result<Vec<Operation>, String> {
+    let conn = library::get_db_connection(&app, &library_id)?;
+    let mut stmt = conn
+        .prepare("SELECT id, name, changes, created_at, undone FROM operation_log ORDER BY id DESC LIMIT ?1")
+        .map_err(|e| utils::treat(e, "Unable to obtain the history"))?;
+    let operation_iter = stmt
+        .query_map(params![limit.unwrap_or(MAX_HISTORY as u32)], |row| {
+            let (operation, _) = deserialize_operation(row)?;
+            Ok(operation)
+        })
+        .map_err(|e| utils::treat(e, "Unable to obtain the history"))?;
+
+    let mut operations = Vec::new();
+    for operation in operation_iter {
+        operations.push(operation.map_err(|e| utils::treat(e, "Unable to obtain the history"))?);
+    }
+
+    Ok(operations)
+}
+
+#[tauri::command]
+pub async fn undo(app: tauri::AppHandle, library_id: String) -> Result<Option<Operation>, String> {
+    replay(&app, &library_id, true)
+}
+
+#[tauri::command]
+pub async fn redo(app: tauri::AppHandle, library_id: String) -> Result<Option<Operation>, String> {
+    replay(&app, &library_id, false)
+}
+
+fn replay(app: &tauri::AppHandle, library_id: &str, reverse: bool) -> Result<Option<Operation>, String> {
+    let library_root = library::get_library_root_path(app, library_id)?;
+    let mut conn = library::get_db_connection(app, library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    tx.pragma_update(None, "defer_foreign_keys", true).map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+
+    let Some((operation, changes)) = apply_operation(&tx, reverse)? else {
+        return Ok(None);
+    };
+    tx.commit().map_err(|e| utils::treat(e, "Unable to commit transaction"))?;
+
+    if let Err(e) = move_files(&library_root, &file_moves(&changes, reverse)) {
+        let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+        tx.pragma_update(None, "defer_foreign_keys", true).map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+        apply_operation(&tx, !reverse)?;
+        tx.commit().map_err(|e| utils::treat(e, "Unable to commit transaction"))?;
+        return Err(e);
+    }
+
+    refresh_items(app, library_id, &library_root, &changes)?;
+    Ok(Some(operation))
+}
+
+fn apply_operation(tx: &Transaction, reverse: bool) -> Result<Option<(Operation, Vec<Change>)>, String> {
+    let sql = if reverse {
+        "SELECT id, name, changes, created_at, undone FROM operation_log WHERE undone = 0 ORDER BY id DESC LIMIT 1"
+    } else {
+        "SELECT id, name, changes, created_at, undone FROM operation_log WHERE undone = 1 ORDER BY id ASC LIMIT 1"
+    };
+    let Some((mut operation, changes)) = tx.query_row(sql, [], deserialize_operation).optional().map_err(|e| utils::treat(e, "Unable to obtain the history"))? else {
+        return Ok(None);
+    };
+
+    if reverse {
+        for change in changes.iter().rev() {
+            apply_change(tx, change, true)?;
+        }
+    } else {
+        for change in &changes {
+            apply_change(tx, change, false)?;
+        }
+    }
+
+    tx.execute("UPDATE operation_log SET undone = ?1 WHERE id = ?2", params![reverse as i32, operation.id])
+        .map_err(|e| utils::treat(e, "Unable to update the history"))?;
+    operation.undone = reverse;
+
+    Ok(Some((operation, changes)))
+}
+
+fn discard<P: rusqlite::Params>(tx: &Transaction, library_root: &Path, condition: &str, params: P) -> Result<(), String> {
+    let mut stmt = tx
+        .prepare(&format!("SELECT id, name, changes, created_at, undone FROM operation_log WHERE {}", condition))
+        .map_err(|e| utils::treat(e, "Unable to obtain the history"))?;
+    let expired = stmt
+        .query_map(params, deserialize_operation)
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| utils::treat(e, "Unable to obtain the history"))?;
+
+    for (operation, changes) in expired {
+        for change in changes {
+            if let Change::Move { from, to } = change {
+                for file in [from, to] {
+                    let path = library_root.join(&file);
+                    if file.starts_with("trash/") && path.exists() {
+                        fs::remove_file(path).map_err(|e| utils::treat(e, "Unable to empty the trash"))?;
+                    }
+                }
+            }
+        }
+        tx.execute("DELETE FROM operation_log WHERE id = ?1", params![operation.id]).map_err(|e| utils::treat(e, "Unable to update the history"))?;
+    }
+
+    Ok(())
+}
+
+fn file_moves(changes: &[Change], reverse: bool) -> Vec<(String, String)> {
+    let moves = changes.iter().filter_map(|change| match change {
+        Change::Move { from, to } if reverse => Some((to.clone(), from.clone())),
+        Change::Move { from, to } => Some((from.clone(), to.clone())),
+        _ => None,
+    });
+
+    if reverse {
+        moves.rev().collect()
+    } else {
+        moves.collect()
+    }
+}
+
+pub fn move_files(library_root: &Path, moves: &[(String, String)]) -> Result<(), String> {
+    for (index, (source, destination)) in moves.iter().enumerate() {
+        if let Err(e) = fs::rename(library_root.join(source), library_root.join(destination)) {
+            for (source, destination) in moves[..index].iter().rev() {
+                if let Err(e) = fs::rename(library_root.join(destination), library_root.join(source)) {
+                    log::warn!("Unable to move {} back to {}: {}", destination, source, e);
+                }
+            }
+            return Err(utils::treat(e, "Unable to restore the file"));
+        }
+    }
+
+    Ok(())
+}
+
+fn apply_change(tx: &Transaction, change: &Change, reverse: bool) -> Result<(), String> {
+    match change {
+        Change::Insert { table, row } | Change::Delete { table, row } => {
+            let insert = matches!(change, Change::Insert { .. }) != reverse;
+            if insert {
+                insert_row(tx, table, row)
+            } else {
+                delete_row(tx, table, row)
+            }
+        }
+        Change::Update { table, before, after } => {
+            let (from, to) = if reverse { (after, before) } else { (before, after) };
+            update_row(tx, table, from, to)
+        }
+        Change::Move { .. } => Ok(()),
+    }
+}
+
+fn insert_row(tx: &Transaction, table: &str, row: &Row) -> Result<(), String> {
+    let columns: Vec<&String> = row.keys().collect();
+    let placeholders = vec!["?"; columns.len()].join(", ");
+    let column_list = columns.iter().map(|column| column.as_str()).collect::<Vec<_>>().join(", ");
+    let values = row.values().map(to_sql_value);
+
+    tx.execute(&format!("INSERT OR REPLACE INTO {} ({}) VALUES ({})", table, column_list, placeholders), params_from_iter(values))
+        .map_err(|e| utils::treat(e, "Unable to replay the operation"))?;
+    Ok(())
+}
+
+fn delete_row(tx: &Transaction, table: &str, row: &Row) -> Result<(), String> {
+    let keys = key_columns(table);
+    let condition = keys.iter().map(|key| format!("{} = ?", key)).collect::<Vec<_>>().join(" AND ");
+    let values = keys.iter().map(|key| to_sql_value(row.get(*key).unwrap_or(&JsonValue::Null)));
+
+    tx.execute(&format!("DELETE FROM {} WHERE {}", table, condition), params_from_iter(values))
+        .map_err(|e| utils::treat(e, "Unable to replay the operation"))?;
+    Ok(())
+}
+
+fn update_row(tx: &Transaction, table: &str, from: &Row, to: &Row) -> Result<(), String> {
+    let keys = key_columns(table);
+    let changed: Vec<(&String, &JsonValue)> = to.iter().filter(|(column, value)| from.get(*column) != Some(*value)).collect();
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    let assignments = changed.iter().map(|(column, _)| format!("{} = ?", column)).collect::<Vec<_>>().join(", ");
+    let condition = keys.iter().map(|key| format!("{} = ?", key)).collect::<Vec<_>>().join(" AND ");
+    let values = changed
+        .into_iter()
+        .map(|(_, value)| value)
+        .chain(keys.iter().map(|key| to.get(*key).unwrap_or(&JsonValue::Null)))
+        .map(to_sql_value);
+
+    tx.execute(&format!("UPDATE {} SET {} WHERE {}", table, assignments, condition), params_from_iter(values))
+        .map_err(|e| utils::treat(e, "Unable to replay the operation"))?;
+    Ok(())
+}
+
+fn refresh_items(app: &tauri::AppHandle, library_id: &str, library_root: &Path, changes: &[Change]) -> Result<(), String> {
+    let mut item_ids = HashSet::new();
+    let mut rerender = HashSet::new();
+    let mut tag_ids = Vec::new();
+
+    for change in changes {
+        match change {
+            Change::Insert { table, row } | Change::Delete { table, row } => {
+                let column = if table == "item" { "id" } else { "item_id" };
+                if let Some(JsonValue::String(item_id)) = row.get(column) {
+                    item_ids.insert(item_id.clone());
+                }
+            }
+            Change::Update { table, before, after } if table == "item" => {
+                if let Some(JsonValue::String(item_id)) = after.get("id") {
+                    item_ids.insert(item_id.clone());
+                    if before.get("edits") != after.get("edits") || before.get("checksum") != after.get("checksum") {
+                        rerender.insert(item_id.clone());
+                    }
+                }
+            }
+            Change::Update { table, after, .. } if table == "tag" => {
+                if let Some(JsonValue::String(tag_id)) = after.get("id") {
+                    tag_ids.push(tag_id.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let conn = library::get_db_connection(app, library_id)?;
+    if !tag_ids.is_empty() {
+        let placeholders = vec!["?"; tag_ids.len()].join(", ");
+        let mut stmt = conn
+            .prepare(&format!("SELECT DISTINCT item_id FROM item_tag WHERE tag_id IN ({})", placeholders))
+            .map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+        let tagged = stmt
+            .query_map(params_from_iter(&tag_ids), |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+        item_ids.extend(tagged);
+    }
+    let mut existing = Vec::new();
+    for item_id in item_ids {
+        let item = conn
+            .query_row("SELECT * FROM item WHERE id = ?1", params![item_id], utils::deserialize_item)
+            .optional()
+            .map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+
+        if let Some(item) = item {
+            if rerender.contains(&item.id) {
+                edits::clear_render_cache(library_root, &item.id)?;
+                edits::render_previews(library_root, &item)?;
+            }
+            existing.push(item.id);
+        }
+    }
+
+    sidecar::sync_items(app, library_id, &existing)
+}
+
+fn read_rows(tx: &Transaction, table: &str, column: &str, ids: &[String]) -> Result<Vec<Row>, String> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    let mut stmt = tx
+        .prepare(&format!("SELECT * FROM {} WHERE {} IN ({})", table, column, placeholders))
+        .map_err(|e| utils::treat(e, "Unable to record the operation"))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+
+    let row_iter = stmt
+        .query_map(params_from_iter(ids), |row| {
+            let mut values = Row::new();
+            for (index, column) in columns.iter().enumerate() {
+                values.insert(column.clone(), to_json_value(row.get::<_, Value>(index)?));
+            }
+            Ok(values)
+        })
+        .map_err(|e| utils::treat(e, "Unable to record the operation"))?;
+
+    let mut rows = Vec::new();
+    for row in row_iter {
+        rows.push(row.map_err(|e| utils::treat(e, "Unable to record the operation"))?);
+    }
+
+    Ok(rows)
+}
+
+fn deserialize_operation(row: &rusqlite::Row<'_>) -> Result<(Operation, Vec<Change>), rusqlite::Error> {
+    let changes: Vec<Change> = serde_json::from_str(&row.get::<_, String>(2)?)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?;
+    let operation = Operation {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
+            .map_err(|_| {
+                rusqlite::Error::InvalidColumnType(3, "created_at".to_string(), rusqlite::types::Type::Text)
+            })?.with_timezone(&Utc),
+        undone: row.get::<_, i32>(4)? != 0,
+        change_count: changes.len(),
+    };
+
+    Ok((operation, changes))
+}
+
+fn key_columns(table: &str) -> &'static [&'static str] {
+    match table {
+        "album_item" => &["album_id", "item_id"],
+        "item_tag" => &["item_id", "tag_id"],
+        _ => &["id"],
+    }
+}
+
+fn row_key(row: &Row, keys: &[&str]) -> String {
+    keys.iter().map(|key| row.get(*key).map(|value| value.to_string()).unwrap_or_default()).collect::<Vec<_>>().join("\u{1f}")
+}
+
+fn to_json_value(value: Value) -> JsonValue {
+    match value {
+        Value::Null | Value::Blob(_) => JsonValue::Null,
+        Value::Integer(value) => JsonValue::Number(value.into()),
+        Value::Real(value) => Number::from_f64(value).map_or(JsonValue::Null, JsonValue::Number),
+        Value::Text(value) => JsonValue::String(value),
+    }
+}
+
+fn to_sql_value(value: &JsonValue) -> Value {
+    match value {
+        JsonValue::Null => Value::Null,
+        JsonValue::Bool(value) => Value::Integer(*value as i64),
+        JsonValue::Number(value) => value.as_i64().map_or_else(|| Value::Real(value.as_f64().unwrap_or_default()), Value::Integer),
+        JsonValue::String(value) => Value::Text(value.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::schema;
+
+    fn tag_paths(tx: &Transaction) -> Vec<String> {
+        let mut stmt = tx.prepare("SELECT path FROM tag ORDER BY path").unwrap();
+        stmt.query_map([], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn deleted_rows_are_restored_and_removed_again() {
+        let mut conn = schema::base_library();
+        schema::migrate(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO item (id, original_name, file_type, file_size, width, height, checksum, created_at) VALUES ('item', 'a.jpg', 'image/jpeg', 1, 1, 1, '', '');
+            INSERT INTO tag (id, name, parent, path, created_at) VALUES ('travel', 'Travel', NULL, 'Travel', ''), ('japan', 'Japan', 'travel', 'Travel/Japan', '');
+            INSERT INTO item_tag VALUES ('item', 'japan', '');",
+        ).unwrap();
+        let tx = conn.transaction().unwrap();
+        let ids = vec!["travel".to_string(), "japan".to_string()];
+        let snapshots = vec![capture(&tx, "tag", "id", &ids).unwrap(), capture(&tx, "item_tag", "item_id", &["item".to_string()]).unwrap()];
+        tx.execute_batch("DELETE FROM item_tag; DELETE FROM tag;").unwrap();
+        record(&tx, "delete_tag", snapshots).unwrap();
+
+        tx.pragma_update(None, "defer_foreign_keys", true).unwrap();
+        let (operation, changes) = apply_operation(&tx, true).unwrap().unwrap();
+        assert_eq!(operation.name, "delete_tag");
+        assert_eq!(changes.len(), 3);
+        assert_eq!(tag_paths(&tx), vec!["Travel", "Travel/Japan"]);
+
+        apply_operation(&tx, false).unwrap().unwrap();
+        assert!(tag_paths(&tx).is_empty());
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn moves_are_swapped_on_undo() {
+        let root = std::env::temp_dir().join(format!("journal-{}", std::process::id()));
+        fs::create_dir_all(root.join("originals")).unwrap();
+        fs::create_dir_all(root.join("trash")).unwrap();
+        fs::write(root.join("originals/a.jpg"), "rotated").unwrap();
+        fs::write(root.join("trash/old.jpg"), "original").unwrap();
+
+        let changes = [
+            Change::Move { from: "originals/a.jpg".to_string(), to: "trash/old.jpg".to_string() },
+            Change::Move { from: "trash/new.jpg".to_string(), to: "originals/a.jpg".to_string() },
+        ];
+        move_files(&root, &file_moves(&changes, true)).unwrap();
+
+        assert_eq!(fs::read_to_string(root.join("originals/a.jpg")).unwrap(), "original");
+        assert_eq!(fs::read_to_string(root.join("trash/new.jpg")).unwrap(), "rotated");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn failed_moves_put_earlier_files_back() {
+        let root = std::env::temp_dir().join(format!("journal-failed-{}", std::process::id()));
+        fs::create_dir_all(root.join("originals")).unwrap();
+        fs::create_dir_all(root.join("trash")).unwrap();
+        fs::write(root.join("originals/a.jpg"), "photo").unwrap();
+
+        let changes = [
+            Change::Move { from: "originals/a.jpg".to_string(), to: "trash/a.jpg".to_string() },
+            Change::Move { from: "originals/missing.jpg".to_string(), to: "trash/missing.jpg".to_string() },
+        ];
+        assert!(move_files(&root, &file_moves(&changes, false)).is_err());
+
+        assert_eq!(fs::read_to_string(root.join("originals/a.jpg")).unwrap(), "photo");
+        assert!(!root.join("trash/a.jpg").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
\ No newline at end of file
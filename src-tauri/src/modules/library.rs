@@ -1,24 +1,118 @@
 use chrono::Utc;
+use image::codecs::gif::{GifEncoder, Repeat};
 use image::{DynamicImage, GenericImageView, ImageFormat};
-use rusqlite::{params, Connection};
+use rusqlite::{params, params_from_iter, Connection, Transaction};
+use serde::Serialize;
 use serde_json::Value;
 use rayon::prelude::*;
 use std::fs;
-use std::io::Cursor;
+use std::io::BufReader;
 use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, PoisonError};
 use uuid::Uuid;
 
+use crate::modules::color;
 use crate::modules::config;
+use crate::modules::edits;
+use crate::modules::hash;
+use crate::modules::iptc::IptcData;
+use crate::modules::journal;
+use crate::modules::query::{self, ItemQuery};
+use crate::modules::schema;
+use crate::modules::sidecar;
+use crate::modules::staging::{self, ImportStage, UnremovedSource};
+use crate::modules::tags;
 use crate::modules::utils;
+use crate::modules::xmp::XmpData;
 
-fn get_db_connection(app: &tauri::AppHandle, library_id: &str) -> Result<Connection, String> {
+const IMPORT_MEMORY_BUDGET: u64 = 1024 * 1024 * 1024;
+
+static IMPORT_BUDGET: ByteBudget = ByteBudget::new(IMPORT_MEMORY_BUDGET);
+
+static RECOVERED_LIBRARIES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub struct PreparedItem {
+    pub item: utils::Item,
+    pub keywords: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    Copy,
+    Move,
+    Reference,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub items: Vec<utils::Item>,
+    pub unremoved_sources: Vec<UnremovedSource>,
+}
+
+struct ByteBudget {
+    limit: u64,
+    in_flight: Mutex<u64>,
+    released: Condvar,
+}
+
+struct BudgetPermit<'a> {
+    budget: &'a ByteBudget,
+    bytes: u64,
+}
+
+impl ByteBudget {
+    const fn new(limit: u64) -> ByteBudget {
+        ByteBudget { limit, in_flight: Mutex::new(0), released: Condvar::new() }
+    }
+
+    fn acquire(&self, bytes: u64) -> BudgetPermit<'_> {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(PoisonError::into_inner);
+        while *in_flight > 0 && *in_flight + bytes > self.limit {
+            in_flight = self.released.wait(in_flight).unwrap_or_else(PoisonError::into_inner);
+        }
+        *in_flight += bytes;
+        BudgetPermit { budget: self, bytes }
+    }
+}
+
+impl Drop for BudgetPermit<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.budget.in_flight.lock().unwrap_or_else(PoisonError::into_inner);
+        *in_flight -= self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+pub fn close_library(library_id: &str) {
+    RECOVERED_LIBRARIES.lock().unwrap_or_else(PoisonError::into_inner).retain(|id| id != library_id);
+}
+
+pub fn get_db_connection(app: &tauri::AppHandle, library_id: &str) -> Result<Connection, String> {
     let meta_path = get_library_root_path(app, library_id)?;
-    let db_path = meta_path.join("lib.db");
-    Connection::open(db_path).map_err(|e| utils::treat(e, "Unable to open database"))
+    let conn = open_connection(&meta_path)?;
+
+    let mut recovered = RECOVERED_LIBRARIES.lock().unwrap_or_else(PoisonError::into_inner);
+    if !recovered.iter().any(|id| id == library_id) {
+        staging::recover_imports(&conn, &meta_path)?;
+        recovered.push(library_id.to_string());
+
+        if hash::needs_rehash(&conn)? {
+            hash::start_rehash(app.clone(), library_id.to_string(), meta_path.clone(), open_connection(&meta_path)?);
+        }
+    }
+
+    Ok(conn)
+}
+
+fn open_connection(library_root: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(library_root.join("lib.db")).map_err(|e| utils::treat(e, "Unable to open database"))?;
+    conn.pragma_update(None, "foreign_keys", true).map_err(|e| utils::treat(e, "Unable to open database"))?;
+    schema::migrate(&conn)?;
+    Ok(conn)
 }
 
-fn get_library_root_path(app: &tauri::AppHandle, library_id: &str) -> Result<PathBuf, String> {
-    let store = config::get_store(&app)?;
+pub fn get_library_root_path(app: &tauri::AppHandle, library_id: &str) -> Result<PathBuf, String> {
+    let store = config::get_store(app)?;
     let libraries = match store.get("libraries") {
         Some(Value::Array(arr)) => arr,
         _ => vec![],
@@ -34,11 +128,13 @@ fn get_library_root_path(app: &tauri::AppHandle, library_id: &str) -> Result<Pat
 }
 
 #[tauri::command]
-pub fn get_items(app: tauri::AppHandle, library_id: String) -> Result<Vec<utils::Item>, String> {
+pub fn get_items(app: tauri::AppHandle, library_id: String, query: Option<ItemQuery>) -> Result<Vec<utils::Item>, String> {
     let conn = get_db_connection(&app, &library_id)?;
-    let mut stmt = conn.prepare("SELECT * FROM item ORDER BY created_at DESC").map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+    let (conditions, values) = query.unwrap_or_default().to_sql();
+    let sql = format!("SELECT * FROM item{} ORDER BY created_at DESC", query::where_clause(&conditions));
+    let mut stmt = conn.prepare(&sql).map_err(|e| utils::treat(e, "Unable to obtain items"))?;
 
-    let item_iter = stmt.query_map([], |row| utils::deserialize_item(row)).map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+    let item_iter = stmt.query_map(params_from_iter(values), utils::deserialize_item).map_err(|e| utils::treat(e, "Unable to obtain items"))?;
 
     let mut items = Vec::new();
     for item in item_iter {
@@ -49,127 +145,277 @@ pub fn get_items(app: tauri::AppHandle, library_id: String) -> Result<Vec<utils:
 }
 
 #[tauri::command]
-pub async fn add_items(app: tauri::AppHandle, library_id: String, source_paths: Vec<String>, delete_source: bool) -> Result<Vec<utils::Item>, String> {
+pub async fn add_items(app: tauri::AppHandle, library_id: String, source_paths: Vec<String>, delete_source: bool, reference: Option<bool>) -> Result<ImportReport, String> {
+    let mode = match (reference.unwrap_or(false), delete_source) {
+        (true, true) => return Err(utils::treat_msg("Referenced items cannot delete their source files")),
+        (true, false) => ImportMode::Reference,
+        (false, true) => ImportMode::Move,
+        (false, false) => ImportMode::Copy,
+    };
+
     let library_root = get_library_root_path(&app, &library_id)?;
-    let originals_dir = library_root.join("originals");
-    fs::create_dir_all(&originals_dir).map_err(|e| utils::treat(e, "Unable to create required directory"))?;
-    let thumbs_dir = library_root.join("thumbnails");
-    fs::create_dir_all(&thumbs_dir).map_err(|e| utils::treat(e, "Unable to create required directory"))?;
+    let mut conn = get_db_connection(&app, &library_id)?;
+    let stage = ImportStage::begin(&conn, &library_root)?;
 
-    let items: Result<Vec<utils::Item>, String> = source_paths
+    let prepared: Result<Vec<PreparedItem>, String> = source_paths
         .par_iter()
-        .map(|path| prepare_item(path, &originals_dir, &thumbs_dir, delete_source))
+        .map(|path| prepare_item(path, &stage, mode))
         .collect();
 
-    let items = items?;
+    let prepared = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            stage.abort(&conn)?;
+            return Err(e);
+        }
+    };
+    let (_, unremoved_sources) = stage.commit(&mut conn, |tx| insert_items(tx, &prepared))?;
 
-    let mut conn = get_db_connection(&app, &library_id)?;
-    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let items: Vec<utils::Item> = prepared.into_iter().map(|prepared| prepared.item).collect();
+    let item_ids: Vec<String> = items.iter().map(|item| item.id.clone()).collect();
+    if let Err(e) = sidecar::sync_items(&app, &library_id, &item_ids) {
+        log::warn!("Unable to write the sidecars of imported items: {}", e);
+    }
+
+    Ok(ImportReport { items, unremoved_sources })
+}
+
+pub fn insert_items(tx: &Transaction, prepared: &[PreparedItem]) -> Result<(), String> {
+    let mut stmt = tx.prepare(
+        "INSERT INTO item (
+            id,
+            original_name,
+            file_type,
+            file_size,
+            width,
+            height,
+            checksum,
+            is_favorite,
+            is_screenshot,
+            is_screen_recording,
+            live_video,
+            created_at,
+            page_count,
+            is_animated,
+            frame_count,
+            duration,
+            color_space,
+            is_hdr,
+            has_gain_map,
+            bit_depth,
+            camera_model,
+            place_name,
+            rating,
+            color_label,
+            flag,
+            title,
+            caption,
+            notes,
+            latitude,
+            longitude,
+            crop,
+            time_zone,
+            original_created_at,
+            original_time_zone,
+            edits,
+            checksum_algorithm,
+            source_path,
+            is_offline
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38)"
+    ).map_err(|e| utils::treat(e, "Unable to prepare statement"))?;
 
-    {
-        let mut stmt = tx.prepare(
-            "INSERT INTO item (
-                id,
-                original_name,
-                file_type,
-                file_size,
-                width,
-                height,
-                checksum,
-                is_favorite,
-                is_screenshot,
-                is_screen_recording,
-                live_video,
-                created_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
-        ).map_err(|e| utils::treat(e, "Unable to prepare statement"))?;
-
-        for item in &items {
-            stmt.execute(params![
-                item.id,
-                item.original_name,
-                item.file_type,
-                item.file_size,
-                item.width,
-                item.height,
-                item.checksum,
-                item.is_favorite as i32,
-                item.is_screenshot as i32,
-                item.is_screen_recording as i32,
-                item.live_video,
-                item.created_at.to_rfc3339()
-            ]).map_err(|e| utils::treat(e, "Unable to import item to the library"))?;
+    for PreparedItem { item, keywords } in prepared {
+        stmt.execute(params![
+            item.id,
+            item.original_name,
+            item.file_type,
+            item.file_size,
+            item.width,
+            item.height,
+            item.checksum,
+            item.is_favorite as i32,
+            item.is_screenshot as i32,
+            item.is_screen_recording as i32,
+            item.live_video,
+            item.created_at.to_rfc3339(),
+            item.page_count,
+            item.is_animated as i32,
+            item.frame_count,
+            item.duration,
+            item.color_space,
+            item.is_hdr as i32,
+            item.has_gain_map as i32,
+            item.bit_depth,
+            item.camera_model,
+            item.place_name,
+            item.rating,
+            item.color_label,
+            item.flag,
+            item.title,
+            item.caption,
+            item.notes,
+            item.latitude,
+            item.longitude,
+            item.crop.and_then(|crop| serde_json::to_string(&crop).ok()),
+            item.time_zone,
+            item.original_created_at.map(|date| date.to_rfc3339()),
+            item.original_time_zone,
+            if item.edits.is_empty() { None } else { serde_json::to_string(&item.edits).ok() },
+            item.checksum_algorithm.name(),
+            item.source_path,
+            item.is_offline as i32
+        ]).map_err(|e| utils::treat(e, "Unable to import item to the library"))?;
+
+        for keyword in keywords {
+            let tag_id = tags::ensure_tag_path(tx, keyword)?;
+            tx.execute(
+                "INSERT OR IGNORE INTO item_tag (item_id, tag_id, added_at) VALUES (?1, ?2, ?3)",
+                params![item.id, tag_id, Utc::now().to_rfc3339()],
+            ).map_err(|e| utils::treat(e, "Unable to import item to the library"))?;
         }
     }
 
-    tx.commit().map_err(|e| utils::treat(e, "Unable to commit transaction"))?;
-
-    Ok(items)
+    Ok(())
 }
 
-fn prepare_item(source_path_str: &str, originals_dir: &Path, thumbs_dir: &Path, delete_source: bool) -> Result<utils::Item, String> {
+pub fn prepare_item(source_path_str: &str, stage: &ImportStage, mode: ImportMode) -> Result<PreparedItem, String> {
     let source_path = Path::new(source_path_str);
     if !source_path.exists() {
         return Err(format!("Source file does not exist: {}", source_path_str));
     }
 
+    let original_name = source_path.file_name().and_then(|n| n.to_str()).ok_or("Invalid file name")?;
+    let file_extension = source_path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+
+    if !utils::is_supported_extension(file_extension) {
+        return Err(format!("Unable to import \"{}\": {}", original_name, utils::unsupported_file_type(file_extension)));
+    }
+
+    let reference_path = match mode {
+        ImportMode::Reference => Some(fs::canonicalize(source_path).map_err(|e| utils::treat(e, "Unable to resolve the source path"))?.to_string_lossy().to_string()),
+        _ => None,
+    };
+
+    let item_id = Uuid::new_v4().to_string();
+    let file_name = format!("{}.{}", item_id, file_extension);
+    let thumbs_dir = stage.dir("thumbnails");
+    let previews_dir = stage.dir("previews");
+    let staged_path = stage.dir("originals").join(&file_name);
+
+    let decoded_size = utils::decoded_size(source_path, file_extension).ok_or_else(|| utils::treat_msg("Unable to load image"))?;
+    let (checksum, file_size, original_path) = match mode {
+        ImportMode::Reference => {
+            let file_size = fs::metadata(source_path).map_err(|e| utils::treat(e, "Unable to read photo data"))?.len();
+            (hash::hash_file(source_path, hash::CURRENT_ALGORITHM)?, file_size, source_path)
+        }
+        ImportMode::Copy => {
+            let (checksum, file_size) = hash::copy_file(source_path, &staged_path, hash::CURRENT_ALGORITHM)?;
+            (checksum, file_size, staged_path.as_path())
+        }
+        ImportMode::Move => {
+            let (checksum, file_size) = stage.stage_move(source_path, &file_name)?;
+            (checksum, file_size, staged_path.as_path())
+        }
+    };
+
+    let permit = IMPORT_BUDGET.acquire(decoded_size);
+    let mut prepared = describe_original(source_path, original_path, &item_id, checksum, file_size, &thumbs_dir, &previews_dir);
+    drop(permit);
+    match &mut prepared {
+        Ok(prepared) => prepared.item.source_path = reference_path,
+        Err(_) => {
+            if mode == ImportMode::Move {
+                if let Err(e) = stage.unstage_move(&file_name) {
+                    log::warn!("Unable to restore {}: {}", source_path_str, e);
+                }
+            } else {
+                let _ = fs::remove_file(&staged_path);
+            }
+            let _ = fs::remove_file(thumbs_dir.join(format!("{}.webp", item_id)));
+            let _ = fs::remove_file(previews_dir.join(format!("{}.gif", item_id)));
+        }
+    }
+
+    prepared
+}
+
+fn describe_original(source_path: &Path, original_path: &Path, item_id: &str, checksum: String, file_size: u64, thumbs_dir: &Path, previews_dir: &Path) -> Result<PreparedItem, String> {
     let original_name = source_path.file_name().and_then(|n| n.to_str()).ok_or("Invalid file name")?;
     let file_extension = source_path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
     let file_type = utils::map_extension_to_mime(file_extension);
 
-    let file_data = fs::read(source_path).map_err(|e| utils::treat(e, "Unable to read photo data"))?;
-    let checksum = format!("{:x}", md5::compute(&file_data));
-    let file_size = file_data.len() as u64;
+    let exif_data = fs::File::open(original_path).ok().and_then(|file| exif::Reader::new().read_from_container(&mut BufReader::new(file)).ok());
+    let embedded_xmp = XmpData::from_file(original_path).unwrap_or_default();
+    let xmp_data = match sidecar::read_for_source(source_path) {
+        Some(sidecar_xmp) => sidecar_xmp.or(embedded_xmp),
+        None => embedded_xmp,
+    };
+    let iptc_data = IptcData::from_jpeg_file(original_path).unwrap_or_default();
 
-    let mut image = utils::load_image(&file_data, file_extension)?;
+    let loaded = utils::load_image_file(original_path, file_extension)?;
+    let mut image = loaded.image;
 
     if ["jpg", "jpeg"].contains(&file_extension.to_lowercase().as_str()) {
-        if let Ok(reader) = exif::Reader::new().read_from_container(&mut Cursor::new(&file_data)) {
-            if let Some(orientation_field) = reader.get_field(exif::Tag::Orientation, exif::In::PRIMARY) {
-                let orientation = orientation_field.value.get_uint(0).unwrap_or(1);
-
-                image = match orientation {
-                    2 => image.fliph(),
-                    3 => image.rotate180(),
-                    4 => image.flipv(),
-                    5 => image.rotate90().fliph(),
-                    6 => image.rotate90(),
-                    7 => image.rotate270().fliph(),
-                    8 => image.rotate270(),
-                    _ => image,
-                };
-            }
+        if let Some(reader) = &exif_data {
+            image = utils::apply_orientation(image, utils::exif_orientation(reader));
         }
     }
 
     let (width, height) = image.dimensions();
-    let item_id = Uuid::new_v4().to_string();
-    let file_name = format!("{}.{}", item_id, file_extension);
-
-    let dest_path = originals_dir.join(&file_name);
-    fs::copy(source_path, &dest_path).map_err(|e| utils::treat(e, "Unable to copy item"))?;
 
     let thumb_path = thumbs_dir.join(format!("{}.webp", item_id));
-    generate_thumbnail(&image, &thumb_path)?;
+    generate_thumbnail(&image, loaded.icc_profile.as_deref(), loaded.hdr.transfer, &thumb_path)?;
+    drop(image);
 
-    if delete_source {
-        let _ = fs::remove_file(source_path);
+    if let Some(animation) = &loaded.animation {
+        let preview_path = previews_dir.join(format!("{}.gif", item_id));
+        generate_animated_preview(animation, &preview_path)?;
     }
 
-    Ok(utils::Item {
-        id: item_id,
+    let item = utils::Item {
+        id: item_id.to_string(),
         original_name: original_name.to_string(),
         file_type: file_type.to_string(),
         file_size,
         width,
         height,
         checksum,
+        checksum_algorithm: hash::CURRENT_ALGORITHM,
         is_favorite: false,
         is_screenshot: false,
         is_screen_recording: false,
         live_video: None,
         created_at: Utc::now(),
-    })
+        page_count: loaded.page_count,
+        is_animated: loaded.animation.is_some(),
+        frame_count: loaded.animation.as_ref().map_or(1, |animation| animation.frames.len() as u32),
+        duration: loaded.animation.as_ref().map(|animation| animation.duration),
+        color_space: loaded.icc_profile.as_deref().and_then(color::color_space_name),
+        is_hdr: loaded.hdr.is_hdr(),
+        has_gain_map: loaded.hdr.has_gain_map,
+        bit_depth: loaded.hdr.bit_depth,
+        camera_model: exif_data.as_ref().and_then(|exif| utils::exif_string(exif, exif::Tag::Model)),
+        place_name: xmp_data.place,
+        rating: xmp_data.rating.map_or(0, |rating| rating.clamp(0, 5) as u8),
+        color_label: xmp_data.label.as_deref().and_then(utils::normalize_color_label),
+        flag: if xmp_data.rating == Some(-1) { -1 } else { 0 },
+        title: xmp_data.title.or(iptc_data.title),
+        caption: xmp_data.description.or(iptc_data.caption),
+        notes: None,
+        latitude: xmp_data.latitude,
+        longitude: xmp_data.longitude,
+        crop: xmp_data.crop,
+        time_zone: None,
+        original_created_at: None,
+        original_time_zone: None,
+        edits: Vec::new(),
+        source_path: None,
+        is_offline: false,
+    };
+
+    let keywords = if xmp_data.keywords.is_empty() { iptc_data.keywords } else { xmp_data.keywords };
+
+    Ok(PreparedItem { item, keywords })
 }
 
 #[tauri::command]
@@ -180,6 +426,7 @@ pub fn set_items_favorite(app: tauri::AppHandle, library_id: String, item_ids: V
 
     let mut conn = get_db_connection(&app, &library_id)?;
     let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item", "id", &item_ids)?;
     for item_id in &item_ids {
         tx.execute(
             "UPDATE item SET is_favorite = ?1 WHERE id = ?2",
@@ -189,15 +436,255 @@ pub fn set_items_favorite(app: tauri::AppHandle, library_id: String, item_ids: V
             ],
         ).map_err(|e| utils::treat(e, "Unable to update the item favorite state"))?;
     }
+    journal::record(&tx, "set_items_favorite", vec![snapshot])?;
     tx.commit().map_err(|e| utils::treat(e, "Unable to save favorite items"))?;
     Ok(())
 }
 
-fn generate_thumbnail(img: &DynamicImage, output_path: &Path) -> Result<(), String> {
-    let thumb = img.thumbnail(512, 512);
+#[tauri::command]
+pub fn set_items_rating(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, value: u8) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+    if value > 5 {
+        return Err(utils::treat_msg("Ratings must be between 0 and 5 stars"));
+    }
+
+    let mut conn = get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item", "id", &item_ids)?;
+    for item_id in &item_ids {
+        tx.execute(
+            "UPDATE item SET rating = ?1 WHERE id = ?2",
+            params![value, item_id],
+        ).map_err(|e| utils::treat(e, "Unable to update the item rating"))?;
+    }
+    journal::record(&tx, "set_items_rating", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save item ratings"))?;
+    sidecar::sync_items(&app, &library_id, &item_ids)
+}
+
+#[tauri::command]
+pub fn set_items_color_label(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, value: Option<String>) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+
+    let label = match value {
+        Some(value) => Some(utils::normalize_color_label(&value).ok_or_else(|| utils::treat_msg("Unknown color label"))?),
+        None => None,
+    };
+
+    let mut conn = get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item", "id", &item_ids)?;
+    for item_id in &item_ids {
+        tx.execute(
+            "UPDATE item SET color_label = ?1 WHERE id = ?2",
+            params![label, item_id],
+        ).map_err(|e| utils::treat(e, "Unable to update the item color label"))?;
+    }
+    journal::record(&tx, "set_items_color_label", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save item color labels"))?;
+    sidecar::sync_items(&app, &library_id, &item_ids)
+}
+
+#[tauri::command]
+pub fn set_items_flag(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, value: i8) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+    if !(-1..=1).contains(&value) {
+        return Err(utils::treat_msg("Flags must be -1 (rejected), 0 (unflagged) or 1 (picked)"));
+    }
+
+    let mut conn = get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item", "id", &item_ids)?;
+    for item_id in &item_ids {
+        tx.execute(
+            "UPDATE item SET flag = ?1 WHERE id = ?2",
+            params![value, item_id],
+        ).map_err(|e| utils::treat(e, "Unable to update the item flag"))?;
+    }
+    journal::record(&tx, "set_items_flag", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save item flags"))?;
+    sidecar::sync_items(&app, &library_id, &item_ids)
+}
+
+#[tauri::command]
+pub fn set_item_details(app: tauri::AppHandle, library_id: String, item_id: String, title: Option<String>, caption: Option<String>, notes: Option<String>) -> Result<(), String> {
+    let mut conn = get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item", "id", std::slice::from_ref(&item_id))?;
+    tx.execute(
+        "UPDATE item SET title = ?1, caption = ?2, notes = ?3 WHERE id = ?4",
+        params![non_empty(title), non_empty(caption), non_empty(notes), item_id],
+    ).map_err(|e| utils::treat(e, "Unable to save the item details"))?;
+    journal::record(&tx, "set_item_details", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save the item details"))?;
+    sidecar::sync_items(&app, &library_id, &[item_id])
+}
+
+#[tauri::command]
+pub fn set_items_title(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, value: Option<String>) -> Result<(), String> {
+    set_items_text(&app, &library_id, &item_ids, "title", non_empty(value))
+}
+
+#[tauri::command]
+pub fn set_items_caption(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, value: Option<String>) -> Result<(), String> {
+    set_items_text(&app, &library_id, &item_ids, "caption", non_empty(value))
+}
+
+#[tauri::command]
+pub fn set_items_notes(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, value: Option<String>) -> Result<(), String> {
+    set_items_text(&app, &library_id, &item_ids, "notes", non_empty(value))
+}
+
+fn set_items_text(app: &tauri::AppHandle, library_id: &str, item_ids: &[String], column: &str, value: Option<String>) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = get_db_connection(app, library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item", "id", item_ids)?;
+    for item_id in item_ids {
+        tx.execute(
+            &format!("UPDATE item SET {} = ?1 WHERE id = ?2", column),
+            params![value, item_id],
+        ).map_err(|e| utils::treat(e, "Unable to save the item details"))?;
+    }
+    journal::record(&tx, &format!("set_items_{}", column), vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save the item details"))?;
+    sidecar::sync_items(app, library_id, item_ids)
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+#[tauri::command]
+pub fn get_item_xmp(app: tauri::AppHandle, library_id: String, item_id: String) -> Result<String, String> {
+    let conn = get_db_connection(&app, &library_id)?;
+    Ok(sidecar::build_item_xmp(&conn, &item_id)?.to_packet())
+}
+
+#[tauri::command]
+pub fn delete_items(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+
+    let library_root = get_library_root_path(&app, &library_id)?;
+    let trash_dir = library_root.join("trash");
+    fs::create_dir_all(&trash_dir).map_err(|e| utils::treat(e, "Unable to create required directory"))?;
+
+    let mut conn = get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshots = vec![
+        journal::capture(&tx, "album_item", "item_id", &item_ids)?,
+        journal::capture(&tx, "item_tag", "item_id", &item_ids)?,
+        journal::capture(&tx, "item", "id", &item_ids)?,
+    ];
+    let mut moves = Vec::new();
+
+    for item_id in &item_ids {
+        let item = tx
+            .query_row("SELECT * FROM item WHERE id = ?1", params![item_id], utils::deserialize_item)
+            .map_err(|e| utils::treat(e, "Item not found"))?;
+        let files = [
+            format!("originals/{}", utils::original_file_name(&item)),
+            format!("originals/{}.xmp", item.id),
+            format!("thumbnails/{}.webp", item.id),
+            format!("previews/{}.webp", item.id),
+            format!("previews/{}.gif", item.id),
+        ];
+
+        for file in files {
+            if library_root.join(&file).exists() {
+                let trashed = format!("trash/{}", file.replace('/', "_"));
+                moves.push((file, trashed));
+            }
+        }
+
+        for table in ["album_item", "item_tag"] {
+            tx.execute(&format!("DELETE FROM {} WHERE item_id = ?1", table), params![item_id])
+                .map_err(|e| utils::treat(e, "Unable to delete the item"))?;
+        }
+        tx.execute("DELETE FROM item WHERE id = ?1", params![item_id]).map_err(|e| utils::treat(e, "Unable to delete the item"))?;
+    }
+
+    journal::record_with_moves(&tx, "delete_items", snapshots, moves.clone())?;
+    journal::move_files(&library_root, &moves)?;
+    if let Err(e) = tx.commit() {
+        let restored: Vec<(String, String)> = moves.into_iter().rev().map(|(file, trashed)| (trashed, file)).collect();
+        if let Err(e) = journal::move_files(&library_root, &restored) {
+            log::warn!("Unable to restore the deleted files: {}", e);
+        }
+        return Err(utils::treat(e, "Unable to delete the items"));
+    }
+
+    for item_id in &item_ids {
+        edits::clear_render_cache(&library_root, item_id)?;
+    }
+
+    Ok(())
+}
+
+pub fn generate_thumbnail(img: &DynamicImage, icc_profile: Option<&[u8]>, transfer: Option<color::HdrTransfer>, output_path: &Path) -> Result<(), String> {
+    let thumb = color::prepare_for_display(img.thumbnail(512, 512), icc_profile, transfer);
 
     let mut out_file = fs::File::create(output_path).map_err(|e| utils::treat(e, "Unable to generate thumbnail"))?;
     thumb.write_to(&mut out_file, ImageFormat::WebP).map_err(|e| utils::treat(e, "Unable to write thumbnail"))?;
 
     Ok(())
+}
+
+fn generate_animated_preview(animation: &utils::Animation, output_path: &Path) -> Result<(), String> {
+    let out_file = fs::File::create(output_path).map_err(|e| utils::treat(e, "Unable to generate animated preview"))?;
+    let mut encoder = GifEncoder::new_with_speed(out_file, 10);
+    encoder.set_repeat(Repeat::Infinite).map_err(|e| utils::treat(e, "Unable to write animated preview"))?;
+
+    encoder.encode_frames(animation.frames.iter().cloned()).map_err(|e| utils::treat(e, "Unable to write animated preview"))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_reservations_run_alone() {
+        let budget = ByteBudget::new(100);
+        let permit = budget.acquire(250);
+        assert_eq!(*budget.in_flight.lock().unwrap(), 250);
+        drop(permit);
+
+        let _small = budget.acquire(40);
+        let _other = budget.acquire(60);
+        assert_eq!(*budget.in_flight.lock().unwrap(), 100);
+    }
+
+    #[test]
+    fn imports_describe_the_file_on_disk() {
+        let conn = schema::base_library();
+        schema::migrate(&conn).unwrap();
+        let root = std::env::temp_dir().join(format!("import-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let source = root.join("photo.png");
+        image::RgbImage::from_pixel(40, 30, image::Rgb([200, 10, 10])).save(&source).unwrap();
+
+        let stage = ImportStage::begin(&conn, &root).unwrap();
+        let PreparedItem { item, .. } = prepare_item(&source.to_string_lossy(), &stage, ImportMode::Copy).unwrap();
+        assert_eq!((item.width, item.height), (40, 30));
+        assert_eq!(item.file_size, fs::metadata(&source).unwrap().len());
+        assert_eq!(item.checksum, hash::hash_file(&source, hash::CURRENT_ALGORITHM).unwrap());
+        assert!(stage.dir("originals").join(format!("{}.png", item.id)).exists());
+        assert!(stage.dir("thumbnails").join(format!("{}.webp", item.id)).exists());
+
+        stage.abort(&conn).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
\ No newline at end of file
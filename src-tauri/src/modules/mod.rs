@@ -1,3 +1,23 @@
+pub mod album;
+pub mod bundle;
+pub mod color;
 pub mod config;
+pub mod dates;
+pub mod edits;
+pub mod embed;
+pub mod export;
+pub mod gallery;
+pub mod hash;
+pub mod integrity;
+pub mod iptc;
+pub mod journal;
 pub mod library;
-pub mod utils;
\ No newline at end of file
+pub mod query;
+pub mod referenced;
+pub mod schema;
+pub mod search;
+pub mod sidecar;
+pub mod staging;
+pub mod tags;
+pub mod utils;
+pub mod xmp;
\ No newline at end of file
@@ -0,0 +1,132 @@
+use rusqlite::types::Value;
+use serde::Deserialize;
+
+use crate::modules::tags;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ItemQuery {
+    pub is_favorite: Option<bool>,
+    pub is_animated: Option<bool>,
+    pub is_hdr: Option<bool>,
+    pub tag_id: Option<String>,
+    pub min_rating: Option<u8>,
+    pub color_label: Option<String>,
+    pub flag: Option<i8>,
+}
+
+impl ItemQuery {
+    pub fn to_sql(&self) -> (Vec<String>, Vec<Value>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        if let Some(is_favorite) = self.is_favorite {
+            conditions.push("item.is_favorite = ?".to_string());
+            values.push(Value::Integer(is_favorite as i64));
+        }
+        if let Some(is_animated) = self.is_animated {
+            conditions.push("item.is_animated = ?".to_string());
+            values.push(Value::Integer(is_animated as i64));
+        }
+        if let Some(is_hdr) = self.is_hdr {
+            conditions.push("item.is_hdr = ?".to_string());
+            values.push(Value::Integer(is_hdr as i64));
+        }
+        if let Some(tag_id) = &self.tag_id {
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM item_tag JOIN tag ON tag.id = item_tag.tag_id JOIN tag AS root ON root.id = ?
+                WHERE item_tag.item_id = item.id AND {})",
+                tags::path_condition("tag.path", "root.path")
+            ));
+            values.push(Value::Text(tag_id.clone()));
+        }
+        if let Some(min_rating) = self.min_rating {
+            conditions.push("item.rating >= ?".to_string());
+            values.push(Value::Integer(min_rating as i64));
+        }
+        if let Some(color_label) = &self.color_label {
+            conditions.push("item.color_label = ? COLLATE NOCASE".to_string());
+            values.push(Value::Text(color_label.clone()));
+        }
+        if let Some(flag) = self.flag {
+            conditions.push("item.flag = ?".to_string());
+            values.push(Value::Integer(flag as i64));
+        }
+
+        (conditions, values)
+    }
+}
+
+pub fn where_clause(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::{params_from_iter, Connection};
+
+    fn library() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE item (id TEXT PRIMARY KEY, is_favorite INTEGER, is_animated INTEGER, is_hdr INTEGER, rating INTEGER, color_label TEXT, flag INTEGER);
+            CREATE TABLE tag (id TEXT PRIMARY KEY, path TEXT NOT NULL);
+            CREATE TABLE item_tag (item_id TEXT NOT NULL, tag_id TEXT NOT NULL);
+            INSERT INTO item VALUES ('a', 1, 0, 0, 5, 'Red', 1), ('b', 0, 1, 1, 3, NULL, 0), ('c', 1, 0, 0, 1, 'green', -1);
+            INSERT INTO tag VALUES ('travel', 'Travel'), ('japan', 'Travel/Japan'), ('lower', 'travel/Beach'), ('like', 'Tr_vel');
+            INSERT INTO item_tag VALUES ('a', 'japan'), ('b', 'lower'), ('c', 'like');",
+        ).unwrap();
+        conn
+    }
+
+    fn matching(conn: &Connection, query: ItemQuery) -> Vec<String> {
+        let (conditions, values) = query.to_sql();
+        let mut stmt = conn.prepare(&format!("SELECT id FROM item{} ORDER BY id", where_clause(&conditions))).unwrap();
+        stmt.query_map(params_from_iter(values), |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn empty_query_has_no_where_clause() {
+        let (conditions, values) = ItemQuery::default().to_sql();
+        assert!(conditions.is_empty());
+        assert!(values.is_empty());
+        assert_eq!(where_clause(&conditions), "");
+    }
+
+    #[test]
+    fn every_condition_binds_one_value() {
+        let query = ItemQuery {
+            is_favorite: Some(true),
+            is_animated: Some(false),
+            is_hdr: Some(false),
+            tag_id: Some("travel".to_string()),
+            min_rating: Some(4),
+            color_label: Some("red".to_string()),
+            flag: Some(1),
+        };
+        let (conditions, values) = query.to_sql();
+        assert_eq!(conditions.len(), 7);
+        assert_eq!(values.len(), 7);
+        assert_eq!(values[0], Value::Integer(1));
+        assert_eq!(values[3], Value::Text("travel".to_string()));
+    }
+
+    #[test]
+    fn filters_select_matching_items() {
+        let conn = library();
+        assert_eq!(matching(&conn, ItemQuery { is_favorite: Some(true), ..Default::default() }), ["a", "c"]);
+        assert_eq!(matching(&conn, ItemQuery { min_rating: Some(3), is_hdr: Some(false), ..Default::default() }), ["a"]);
+        assert_eq!(matching(&conn, ItemQuery { color_label: Some("GREEN".to_string()), ..Default::default() }), ["c"]);
+        assert_eq!(matching(&conn, ItemQuery { flag: Some(-1), ..Default::default() }), ["c"]);
+    }
+
+    #[test]
+    fn tag_filter_includes_children_only() {
+        let conn = library();
+        assert_eq!(matching(&conn, ItemQuery { tag_id: Some("travel".to_string()), ..Default::default() }), ["a"]);
+        assert_eq!(matching(&conn, ItemQuery { tag_id: Some("like".to_string()), ..Default::default() }), ["c"]);
+    }
+}
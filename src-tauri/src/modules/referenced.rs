@@ -0,0 +1,85 @@
+use rusqlite::params;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::modules::hash;
+use crate::modules::library;
+use crate::modules::utils;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ConsolidateReport {
+    pub consolidated: Vec<String>,
+    pub offline: Vec<String>,
+    pub mismatched: Vec<String>,
+}
+
+#[tauri::command]
+pub fn refresh_item_status(app: tauri::AppHandle, library_id: String) -> Result<Vec<String>, String> {
+    let conn = library::get_db_connection(&app, &library_id)?;
+    let mut stmt = conn
+        .prepare("SELECT id, source_path, is_offline FROM item WHERE source_path IS NOT NULL")
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+    let referenced = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<i32>>(2)?.unwrap_or(0) != 0)))
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| utils::treat(e, "Unable to obtain items"))?;
+
+    let mut offline = Vec::new();
+    for (item_id, source_path, was_offline) in referenced {
+        let is_offline = !Path::new(&source_path).is_file();
+        if is_offline != was_offline {
+            conn.execute("UPDATE item SET is_offline = ?1 WHERE id = ?2", params![is_offline as i32, item_id])
+                .map_err(|e| utils::treat(e, "Unable to update the item status"))?;
+        }
+        if is_offline {
+            offline.push(item_id);
+        }
+    }
+
+    Ok(offline)
+}
+
+#[tauri::command]
+pub async fn consolidate_items(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>) -> Result<ConsolidateReport, String> {
+    let mut report = ConsolidateReport::default();
+    if item_ids.is_empty() {
+        return Ok(report);
+    }
+
+    let originals_dir = library::get_library_root_path(&app, &library_id)?.join("originals");
+    fs::create_dir_all(&originals_dir).map_err(|e| utils::treat(e, "Unable to create required directory"))?;
+    let conn = library::get_db_connection(&app, &library_id)?;
+
+    for item_id in &item_ids {
+        let item = conn
+            .query_row("SELECT * FROM item WHERE id = ?1", params![item_id], utils::deserialize_item)
+            .map_err(|e| utils::treat(e, "Item not found"))?;
+        let Some(source_path) = &item.source_path else {
+            continue;
+        };
+
+        let source = Path::new(source_path);
+        if !source.is_file() {
+            conn.execute("UPDATE item SET is_offline = 1 WHERE id = ?1", params![item.id])
+                .map_err(|e| utils::treat(e, "Unable to update the item status"))?;
+            report.offline.push(item.id);
+            continue;
+        }
+
+        let target = originals_dir.join(utils::original_file_name(&item));
+        let (copied, _) = hash::copy_file(source, &target, item.checksum_algorithm)?;
+        if copied != item.checksum || hash::hash_file(&target, item.checksum_algorithm)? != item.checksum {
+            fs::remove_file(&target).map_err(|e| utils::treat(e, "Unable to remove the unverified copy"))?;
+            report.mismatched.push(item.id);
+            continue;
+        }
+
+        conn.execute("UPDATE item SET source_path = NULL, is_offline = 0 WHERE id = ?1", params![item.id])
+            .map_err(|e| utils::treat(e, "Unable to consolidate the item"))?;
+        report.consolidated.push(item.id);
+    }
+
+    Ok(report)
+}
\ No newline at end of file
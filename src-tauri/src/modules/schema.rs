@@ -0,0 +1,280 @@
+use rusqlite::Connection;
+
+use crate::modules::utils;
+
+const MIGRATIONS: &[&str] = &[
+    "ALTER TABLE item ADD COLUMN page_count INTEGER DEFAULT 1;",
+    "ALTER TABLE item ADD COLUMN is_animated INTEGER DEFAULT 0;
+    ALTER TABLE item ADD COLUMN frame_count INTEGER DEFAULT 1;
+    ALTER TABLE item ADD COLUMN duration INTEGER;",
+    "ALTER TABLE item ADD COLUMN color_space TEXT;",
+    "ALTER TABLE item ADD COLUMN is_hdr INTEGER DEFAULT 0;
+    ALTER TABLE item ADD COLUMN has_gain_map INTEGER DEFAULT 0;
+    ALTER TABLE item ADD COLUMN bit_depth INTEGER DEFAULT 8;",
+    "ALTER TABLE item ADD COLUMN camera_model TEXT;
+    ALTER TABLE item ADD COLUMN place_name TEXT;
+    CREATE VIRTUAL TABLE item_search USING fts5(
+        item_id UNINDEXED,
+        original_name,
+        albums,
+        captions,
+        keywords,
+        camera,
+        places,
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    INSERT INTO item_search (item_id, original_name, albums, captions, keywords, camera, places)
+        SELECT item.id, item.original_name, (
+            SELECT group_concat(album.name, ' ') FROM album JOIN album_item ON album_item.album_id = album.id WHERE album_item.item_id = item.id
+        ), '', '', item.camera_model, item.place_name FROM item;
+    CREATE TRIGGER item_search_insert AFTER INSERT ON item BEGIN
+        INSERT INTO item_search (item_id, original_name, albums, captions, keywords, camera, places)
+            VALUES (NEW.id, NEW.original_name, '', '', '', NEW.camera_model, NEW.place_name);
+    END;
+    CREATE TRIGGER item_search_update AFTER UPDATE OF original_name, camera_model, place_name ON item BEGIN
+        UPDATE item_search SET original_name = NEW.original_name, camera = NEW.camera_model, places = NEW.place_name WHERE item_id = NEW.id;
+    END;
+    CREATE TRIGGER item_search_delete AFTER DELETE ON item BEGIN
+        DELETE FROM item_search WHERE item_id = OLD.id;
+    END;
+    CREATE TRIGGER item_search_album_item_insert AFTER INSERT ON album_item BEGIN
+        UPDATE item_search SET albums = (
+            SELECT group_concat(album.name, ' ') FROM album JOIN album_item ON album_item.album_id = album.id WHERE album_item.item_id = NEW.item_id
+        ) WHERE item_id = NEW.item_id;
+    END;
+    CREATE TRIGGER item_search_album_item_delete AFTER DELETE ON album_item BEGIN
+        UPDATE item_search SET albums = (
+            SELECT group_concat(album.name, ' ') FROM album JOIN album_item ON album_item.album_id = album.id WHERE album_item.item_id = OLD.item_id
+        ) WHERE item_id = OLD.item_id;
+    END;
+    CREATE TRIGGER item_search_album_update AFTER UPDATE OF name ON album BEGIN
+        UPDATE item_search SET albums = (
+            SELECT group_concat(album.name, ' ') FROM album JOIN album_item ON album_item.album_id = album.id WHERE album_item.item_id = item_search.item_id
+        ) WHERE item_id IN (SELECT item_id FROM album_item WHERE album_id = NEW.id);
+    END;
+    CREATE TRIGGER item_search_album_delete AFTER DELETE ON album BEGIN
+        UPDATE item_search SET albums = (
+            SELECT group_concat(album.name, ' ') FROM album JOIN album_item ON album_item.album_id = album.id WHERE album_item.item_id = item_search.item_id
+        ) WHERE item_id IN (SELECT item_id FROM album_item WHERE album_id = OLD.id);
+    END;",
+    "ALTER TABLE album ADD COLUMN smart_rule TEXT;
+    ALTER TABLE item ADD COLUMN color_label TEXT;",
+    "CREATE TABLE tag (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        parent TEXT,
+        path TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        FOREIGN KEY (parent) REFERENCES tag (id) ON DELETE CASCADE
+    );
+    CREATE INDEX tag_path ON tag (path);
+    CREATE TABLE item_tag (
+        item_id TEXT NOT NULL,
+        tag_id TEXT NOT NULL,
+        added_at TEXT NOT NULL,
+        PRIMARY KEY (item_id, tag_id),
+        FOREIGN KEY (item_id) REFERENCES item (id) ON DELETE CASCADE,
+        FOREIGN KEY (tag_id) REFERENCES tag (id) ON DELETE CASCADE
+    );
+    CREATE TRIGGER item_search_item_tag_insert AFTER INSERT ON item_tag BEGIN
+        UPDATE item_search SET keywords = (
+            SELECT group_concat(tag.path, ' ') FROM tag JOIN item_tag ON item_tag.tag_id = tag.id WHERE item_tag.item_id = NEW.item_id
+        ) WHERE item_id = NEW.item_id;
+    END;
+    CREATE TRIGGER item_search_item_tag_delete AFTER DELETE ON item_tag BEGIN
+        UPDATE item_search SET keywords = (
+            SELECT group_concat(tag.path, ' ') FROM tag JOIN item_tag ON item_tag.tag_id = tag.id WHERE item_tag.item_id = OLD.item_id
+        ) WHERE item_id = OLD.item_id;
+    END;
+    CREATE TRIGGER item_search_tag_update AFTER UPDATE OF path ON tag BEGIN
+        UPDATE item_search SET keywords = (
+            SELECT group_concat(tag.path, ' ') FROM tag JOIN item_tag ON item_tag.tag_id = tag.id WHERE item_tag.item_id = item_search.item_id
+        ) WHERE item_id IN (SELECT item_id FROM item_tag WHERE tag_id = NEW.id);
+    END;",
+    "ALTER TABLE item ADD COLUMN rating INTEGER DEFAULT 0;
+    ALTER TABLE item ADD COLUMN flag INTEGER DEFAULT 0;",
+    "ALTER TABLE item ADD COLUMN title TEXT;
+    ALTER TABLE item ADD COLUMN caption TEXT;
+    ALTER TABLE item ADD COLUMN notes TEXT;
+    DROP TRIGGER item_search_insert;
+    DROP TRIGGER item_search_update;
+    CREATE TRIGGER item_search_insert AFTER INSERT ON item BEGIN
+        INSERT INTO item_search (item_id, original_name, albums, captions, keywords, camera, places)
+            VALUES (NEW.id, NEW.original_name, '', trim(coalesce(NEW.title, '') || ' ' || coalesce(NEW.caption, '') || ' ' || coalesce(NEW.notes, '')), '', NEW.camera_model, NEW.place_name);
+    END;
+    CREATE TRIGGER item_search_update AFTER UPDATE OF original_name, camera_model, place_name, title, caption, notes ON item BEGIN
+        UPDATE item_search SET
+            original_name = NEW.original_name,
+            captions = trim(coalesce(NEW.title, '') || ' ' || coalesce(NEW.caption, '') || ' ' || coalesce(NEW.notes, '')),
+            camera = NEW.camera_model,
+            places = NEW.place_name
+        WHERE item_id = NEW.id;
+    END;",
+    "ALTER TABLE item ADD COLUMN latitude REAL;
+    ALTER TABLE item ADD COLUMN longitude REAL;
+    ALTER TABLE item ADD COLUMN crop TEXT;",
+    "ALTER TABLE item ADD COLUMN time_zone INTEGER;
+    ALTER TABLE item ADD COLUMN original_created_at TEXT;
+    ALTER TABLE item ADD COLUMN original_time_zone INTEGER;",
+    "ALTER TABLE item ADD COLUMN edits TEXT;",
+    "CREATE TABLE operation_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        changes TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        undone INTEGER DEFAULT 0
+    );",
+    "ALTER TABLE item ADD COLUMN checksum_algorithm TEXT DEFAULT 'md5';
+    CREATE INDEX item_checksum ON item (checksum);",
+    "CREATE TABLE import_journal (
+        id TEXT PRIMARY KEY,
+        state TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );",
+    "CREATE TABLE import_move (
+        import_id TEXT NOT NULL,
+        source TEXT NOT NULL,
+        target TEXT NOT NULL,
+        checksum TEXT NOT NULL,
+        FOREIGN KEY (import_id) REFERENCES import_journal (id) ON DELETE CASCADE
+    );",
+    "ALTER TABLE item ADD COLUMN source_path TEXT;
+    ALTER TABLE item ADD COLUMN is_offline INTEGER DEFAULT 0;",
+    "DELETE FROM item_tag WHERE item_id NOT IN (SELECT id FROM item) OR tag_id NOT IN (SELECT id FROM tag);
+    DELETE FROM album_item WHERE item_id NOT IN (SELECT id FROM item) OR album_id NOT IN (SELECT id FROM album);",
+    "ALTER TABLE import_move ADD COLUMN renamed INTEGER DEFAULT 0;",
+    "INSERT OR IGNORE INTO item_tag (item_id, tag_id, added_at)
+        SELECT item_tag.item_id, (SELECT MIN(kept.id) FROM tag kept WHERE kept.path = tag.path), item_tag.added_at FROM item_tag JOIN tag ON tag.id = item_tag.tag_id;
+    DELETE FROM item_tag WHERE tag_id NOT IN (SELECT MIN(id) FROM tag GROUP BY path);
+    UPDATE tag SET parent = (SELECT MIN(kept.id) FROM tag kept WHERE kept.path = (SELECT parent_tag.path FROM tag parent_tag WHERE parent_tag.id = tag.parent)) WHERE parent IS NOT NULL;
+    DELETE FROM tag WHERE id NOT IN (SELECT MIN(id) FROM tag GROUP BY path);
+    CREATE UNIQUE INDEX tag_sibling_name ON tag (IFNULL(parent, ''), name);",
+];
+
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).map_err(|e| utils::treat(e, "Unable to read the library version"))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.unchecked_transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+        tx.execute_batch(migration).map_err(|e| utils::treat(e, "Unable to upgrade the library"))?;
+        tx.pragma_update(None, "user_version", (index + 1) as i64).map_err(|e| utils::treat(e, "Unable to upgrade the library"))?;
+        tx.commit().map_err(|e| utils::treat(e, "Unable to upgrade the library"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub fn base_library() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE item (
+            id TEXT PRIMARY KEY,
+            original_name TEXT NOT NULL,
+            file_type TEXT NOT NULL,
+            file_size INTEGER NOT NULL,
+            width INTEGER NOT NULL,
+            height INTEGER NOT NULL,
+            checksum TEXT NOT NULL,
+            is_favorite INTEGER DEFAULT 0,
+            is_screenshot INTEGER DEFAULT 0,
+            is_screen_recording INTEGER DEFAULT 0,
+            live_video TEXT,
+            created_at TEXT NOT NULL
+        );
+        CREATE TABLE album (id TEXT PRIMARY KEY, name TEXT NOT NULL, description TEXT, parent TEXT, color TEXT, emoji TEXT, created_at TEXT NOT NULL);
+        CREATE TABLE album_item (
+            album_id TEXT NOT NULL,
+            item_id TEXT NOT NULL,
+            added_at TEXT NOT NULL,
+            PRIMARY KEY (album_id, item_id),
+            FOREIGN KEY (album_id) REFERENCES album (id) ON DELETE CASCADE,
+            FOREIGN KEY (item_id) REFERENCES item (id) ON DELETE CASCADE
+        );",
+    ).unwrap();
+    conn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(conn: &Connection, table: &str) -> u32 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn migrations_apply_in_order() {
+        let conn = base_library();
+        migrate(&conn).unwrap();
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        migrate(&conn).unwrap();
+    }
+
+    #[test]
+    fn orphaned_links_are_removed() {
+        let conn = base_library();
+        conn.pragma_update(None, "foreign_keys", false).unwrap();
+        let cleanup = MIGRATIONS.iter().position(|migration| migration.starts_with("DELETE FROM item_tag")).unwrap();
+        for migration in &MIGRATIONS[..cleanup] {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.pragma_update(None, "user_version", cleanup as i64).unwrap();
+        conn.execute_batch(
+            "INSERT INTO item (id, original_name, file_type, file_size, width, height, checksum, created_at) VALUES ('kept', 'a.jpg', 'image/jpeg', 1, 1, 1, '', '');
+            INSERT INTO album (id, name, created_at) VALUES ('album', 'Trip', '');
+            INSERT INTO tag (id, name, path, created_at) VALUES ('tag', 'Travel', 'Travel', '');
+            INSERT INTO album_item VALUES ('album', 'kept', ''), ('album', 'gone', ''), ('missing', 'kept', '');
+            INSERT INTO item_tag VALUES ('kept', 'tag', ''), ('gone', 'tag', ''), ('kept', 'missing', '');",
+        ).unwrap();
+
+        migrate(&conn).unwrap();
+        assert_eq!(count(&conn, "album_item"), 1);
+        assert_eq!(count(&conn, "item_tag"), 1);
+    }
+
+    #[test]
+    fn duplicate_sibling_tags_are_merged() {
+        let conn = base_library();
+        let dedupe = MIGRATIONS.iter().position(|migration| migration.contains("tag_sibling_name")).unwrap();
+        for migration in &MIGRATIONS[..dedupe] {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.pragma_update(None, "user_version", dedupe as i64).unwrap();
+        conn.execute_batch(
+            "INSERT INTO item (id, original_name, file_type, file_size, width, height, checksum, created_at) VALUES ('a', 'a.jpg', 'image/jpeg', 1, 1, 1, '', ''), ('b', 'b.jpg', 'image/jpeg', 1, 1, 1, '', '');
+            INSERT INTO tag (id, name, parent, path, created_at) VALUES
+                ('1', 'Travel', NULL, 'Travel', ''), ('2', 'Travel', NULL, 'Travel', ''),
+                ('3', 'Japan', '1', 'Travel/Japan', ''), ('4', 'Japan', '2', 'Travel/Japan', ''), ('5', 'Kyoto', '4', 'Travel/Japan/Kyoto', '');
+            INSERT INTO item_tag VALUES ('a', '3', ''), ('a', '4', ''), ('b', '4', '');",
+        ).unwrap();
+
+        migrate(&conn).unwrap();
+        assert_eq!(count(&conn, "tag"), 3);
+        assert_eq!(count(&conn, "item_tag"), 2);
+        let parent: String = conn.query_row("SELECT parent FROM tag WHERE id = '5'", [], |row| row.get(0)).unwrap();
+        assert_eq!(parent, "3");
+        assert!(conn.execute("INSERT INTO tag (id, name, path, created_at) VALUES ('6', 'Travel', 'Travel', '')", []).is_err());
+    }
+
+    #[test]
+    fn deleting_items_cascades_with_foreign_keys() {
+        let conn = base_library();
+        conn.pragma_update(None, "foreign_keys", true).unwrap();
+        migrate(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO item (id, original_name, file_type, file_size, width, height, checksum, created_at) VALUES ('item', 'a.jpg', 'image/jpeg', 1, 1, 1, '', '');
+            INSERT INTO album (id, name, created_at) VALUES ('album', 'Trip', '');
+            INSERT INTO tag (id, name, path, created_at) VALUES ('tag', 'Travel', 'Travel', '');
+            INSERT INTO album_item VALUES ('album', 'item', '');
+            INSERT INTO item_tag VALUES ('item', 'tag', '');",
+        ).unwrap();
+
+        conn.execute("DELETE FROM item WHERE id = 'item'", []).unwrap();
+        assert_eq!(count(&conn, "album_item"), 0);
+        assert_eq!(count(&conn, "item_tag"), 0);
+        assert!(conn.execute("INSERT INTO item_tag VALUES ('missing', 'tag', '')", []).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use rusqlite::params_from_iter;
+use rusqlite::types::Value;
+
+use crate::modules::library;
+use crate::modules::query::{self, ItemQuery};
+use crate::modules::utils;
+
+#[tauri::command]
+pub fn search(app: tauri::AppHandle, library_id: String, text: String, query: Option<ItemQuery>) -> Result<Vec<utils::Item>, String> {
+    let conn = library::get_db_connection(&app, &library_id)?;
+    let (mut conditions, mut values) = query.unwrap_or_default().to_sql();
+
+    let sql = match to_match_expression(&text) {
+        Some(expression) => {
+            conditions.insert(0, "item_search MATCH ?".to_string());
+            values.insert(0, Value::Text(expression));
+            format!(
+                "SELECT item.* FROM item JOIN item_search ON item_search.item_id = item.id{} ORDER BY item_search.rank, item.created_at DESC",
+                query::where_clause(&conditions)
+            )
+        }
+        None => format!("SELECT * FROM item{} ORDER BY created_at DESC", query::where_clause(&conditions)),
+    };
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| utils::treat(e, "Unable to search the library"))?;
+    let item_iter = stmt.query_map(params_from_iter(values), utils::deserialize_item).map_err(|e| utils::treat(e, "Unable to search the library"))?;
+
+    let mut items = Vec::new();
+    for item in item_iter {
+        items.push(item.map_err(|e| utils::treat(e, "Unable to search the library"))?);
+    }
+
+    Ok(items)
+}
+
+fn to_match_expression(text: &str) -> Option<String> {
+    let mut terms = Vec::new();
+
+    for (index, part) in text.split('"').enumerate() {
+        if index % 2 == 1 {
+            let phrase = part.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !phrase.is_empty() {
+                terms.push(format!("\"{}\"", phrase));
+            }
+        } else {
+            terms.extend(part.split_whitespace().map(|word| format!("\"{}\"*", word)));
+        }
+    }
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_become_prefix_terms() {
+        assert_eq!(to_match_expression("  harbour   boat ").as_deref(), Some("\"harbour\"* \"boat\"*"));
+        assert_eq!(to_match_expression("AND OR NOT near*").as_deref(), Some("\"AND\"* \"OR\"* \"NOT\"* \"near*\"*"));
+    }
+
+    #[test]
+    fn quoted_text_becomes_a_phrase() {
+        assert_eq!(to_match_expression("\"lisbon   harbour\" boat").as_deref(), Some("\"lisbon harbour\" \"boat\"*"));
+        assert_eq!(to_match_expression("\"unclosed phrase").as_deref(), Some("\"unclosed phrase\""));
+    }
+
+    #[test]
+    fn embedded_quotes_never_reach_the_expression() {
+        let expression = to_match_expression("O\"Brien \"\" say\"").unwrap();
+        assert_eq!(expression, "\"O\"* \"Brien\" \"say\"");
+
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE VIRTUAL TABLE search USING fts5(text); INSERT INTO search VALUES ('O Brien say');").unwrap();
+        let matches: u32 = conn.query_row("SELECT COUNT(*) FROM search WHERE search MATCH ?1", [expression], |row| row.get(0)).unwrap();
+        assert_eq!(matches, 1);
+    }
+
+    #[test]
+    fn empty_input_matches_everything() {
+        assert_eq!(to_match_expression(""), None);
+        assert_eq!(to_match_expression("  \" \"  "), None);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,47 @@
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::Path;
+
+use crate::modules::config;
+use crate::modules::library;
+use crate::modules::tags;
+use crate::modules::utils;
+use crate::modules::xmp::XmpData;
+
+pub fn read_for_source(source_path: &Path) -> Option<XmpData> {
+    let mut candidates = vec![source_path.with_extension("xmp"), source_path.with_extension("XMP")];
+    if let Some(file_name) = source_path.file_name().and_then(|name| name.to_str()) {
+        candidates.insert(0, source_path.with_file_name(format!("{}.xmp", file_name)));
+    }
+
+    candidates
+        .iter()
+        .find(|candidate| candidate.is_file())
+        .and_then(|candidate| fs::read_to_string(candidate).ok())
+        .map(|packet| XmpData::parse(&packet))
+}
+
+pub fn build_item_xmp(conn: &Connection, item_id: &str) -> Result<XmpData, String> {
+    let item = conn
+        .query_row("SELECT * FROM item WHERE id = ?1", params![item_id], utils::deserialize_item)
+        .map_err(|e| utils::treat(e, "Item not found"))?;
+    let keywords = tags::get_item_tag_paths(conn, item_id)?;
+
+    Ok(XmpData::from_item(&item, keywords))
+}
+
+pub fn sync_items(app: &tauri::AppHandle, library_id: &str, item_ids: &[String]) -> Result<(), String> {
+    if item_ids.is_empty() || !config::get_library_flag(app, library_id, "write_sidecars")? {
+        return Ok(());
+    }
+
+    let originals_dir = library::get_library_root_path(app, library_id)?.join("originals");
+    let conn = library::get_db_connection(app, library_id)?;
+
+    for item_id in item_ids {
+        let packet = build_item_xmp(&conn, item_id)?.to_packet();
+        fs::write(originals_dir.join(format!("{}.xmp", item_id)), packet).map_err(|e| utils::treat(e, "Unable to write the XMP sidecar"))?;
+    }
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,372 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, Transaction};
+use serde::Serialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use uuid::Uuid;
+
+use crate::modules::hash;
+use crate::modules::utils;
+
+const STAGING_DIR: &str = "staging";
+const STAGED_DIRS: [&str; 3] = ["originals", "thumbnails", "previews"];
+
+static ACTIVE_IMPORTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub struct ImportStage {
+    id: String,
+    root: PathBuf,
+    library_root: PathBuf,
+    journal: Mutex<Option<Connection>>,
+}
+
+struct PendingMove {
+    source: String,
+    target: String,
+    renamed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnremovedSource {
+    pub path: String,
+    pub reason: String,
+}
+
+impl ImportStage {
+    pub fn begin(conn: &Connection, library_root: &Path) -> Result<ImportStage, String> {
+        let id = Uuid::new_v4().to_string();
+        conn.execute(
+            "INSERT INTO import_journal (id, state, created_at) VALUES (?1, 'staging', ?2)",
+            params![id, Utc::now().to_rfc3339()],
+        ).map_err(|e| utils::treat(e, "Unable to begin the import"))?;
+
+        ACTIVE_IMPORTS.lock().unwrap_or_else(PoisonError::into_inner).push(id.clone());
+        let stage = ImportStage::open(library_root, id);
+        for dir in STAGED_DIRS {
+            fs::create_dir_all(stage.root.join(dir)).map_err(|e| utils::treat(e, "Unable to create required directory"))?;
+        }
+
+        Ok(stage)
+    }
+
+    fn open(library_root: &Path, id: String) -> ImportStage {
+        ImportStage { root: library_root.join(STAGING_DIR).join(&id), id, library_root: library_root.to_path_buf(), journal: Mutex::new(None) }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn dir(&self, name: &str) -> PathBuf {
+        self.root.join(name)
+    }
+
+    pub fn stage_move(&self, source: &Path, target: &str) -> Result<(String, u64), String> {
+        let source_str = source.to_string_lossy().to_string();
+        self.with_journal(|conn| {
+            conn.execute(
+                "INSERT INTO import_move (import_id, source, target, checksum, renamed) VALUES (?1, ?2, ?3, '', 1)",
+                params![self.id, source_str, target],
+            ).map_err(|e| utils::treat(e, "Unable to record the import"))
+        })?;
+
+        let staged = self.dir("originals").join(target);
+        let moved = match fs::rename(source, &staged) {
+            Ok(()) => fs::metadata(&staged)
+                .map_err(|e| utils::treat(e, "Unable to read photo data"))
+                .and_then(|metadata| Ok((hash::hash_file(&staged, hash::CURRENT_ALGORITHM)?, metadata.len()))),
+            Err(e) if e.kind() == ErrorKind::CrossesDevices => self.copy_into_stage(source, &staged, target),
+            Err(e) => Err(utils::treat(e, "Unable to move the file into the library")),
+        };
+        let moved = moved.and_then(|(checksum, size)| {
+            self.with_journal(|conn| {
+                conn.execute("UPDATE import_move SET checksum = ?3 WHERE import_id = ?1 AND target = ?2", params![self.id, target, checksum])
+                    .map_err(|e| utils::treat(e, "Unable to record the import"))
+            })?;
+            Ok((checksum, size))
+        });
+
+        if moved.is_err() {
+            if let Err(e) = self.unstage_move(target) {
+                log::warn!("Unable to restore {}: {}", source_str, e);
+            }
+        }
+        moved
+    }
+
+    pub fn unstage_move(&self, target: &str) -> Result<(), String> {
+        self.with_journal(|conn| self.restore_moves(conn, Some(target)))
+    }
+
+    pub fn commit<T>(self, conn: &mut Connection, write: impl FnOnce(&Transaction) -> Result<T, String>) -> Result<(T, Vec<UnremovedSource>), String> {
+        let value = match self.record(conn, write) {
+            Ok(value) => value,
+            Err(e) => {
+                self.abort(conn)?;
+                return Err(e);
+            }
+        };
+
+        let unremoved = self.publish(conn)?;
+        self.finish(conn)?;
+        Ok((value, unremoved))
+    }
+
+    pub fn abort(self, conn: &Connection) -> Result<(), String> {
+        self.restore_moves(conn, None)?;
+        self.finish(conn)
+    }
+
+    fn copy_into_stage(&self, source: &Path, staged: &Path, target: &str) -> Result<(String, u64), String> {
+        self.with_journal(|conn| {
+            conn.execute("UPDATE import_move SET renamed = 0 WHERE import_id = ?1 AND target = ?2", params![self.id, target])
+                .map_err(|e| utils::treat(e, "Unable to record the import"))
+        })?;
+
+        let (checksum, size) = hash::copy_file(source, staged, hash::CURRENT_ALGORITHM)?;
+        if hash::hash_file(staged, hash::CURRENT_ALGORITHM)? != checksum {
+            return Err(utils::treat_msg("The copied file does not match the source"));
+        }
+        Ok((checksum, size))
+    }
+
+    fn with_journal<T>(&self, write: impl FnOnce(&Connection) -> Result<T, String>) -> Result<T, String> {
+        let mut journal = self.journal.lock().unwrap_or_else(PoisonError::into_inner);
+        if journal.is_none() {
+            *journal = Some(Connection::open(self.library_root.join("lib.db")).map_err(|e| utils::treat(e, "Unable to open database"))?);
+        }
+        journal.as_ref().map_or_else(|| Err(utils::treat_msg("Unable to open database")), write)
+    }
+
+    fn moves(&self, conn: &Connection) -> Result<Vec<PendingMove>, String> {
+        let mut stmt = conn.prepare("SELECT source, target, renamed FROM import_move WHERE import_id = ?1").map_err(|e| utils::treat(e, "Unable to read the import journal"))?;
+        let moves = stmt
+            .query_map(params![self.id], |row| Ok(PendingMove { source: row.get(0)?, target: row.get(1)?, renamed: row.get::<_, i32>(2)? != 0 }))
+            .map_err(|e| utils::treat(e, "Unable to read the import journal"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| utils::treat(e, "Unable to read the import journal"))?;
+        Ok(moves)
+    }
+
+    fn restore_moves(&self, conn: &Connection, target: Option<&str>) -> Result<(), String> {
+        for pending in self.moves(conn)? {
+            if target.is_some_and(|target| target != pending.target) {
+                continue;
+            }
+
+            let source = Path::new(&pending.source);
+            let staged = self.dir("originals").join(&pending.target);
+            if pending.renamed && staged.exists() && !source.exists() {
+                fs::rename(&staged, source).map_err(|e| utils::treat(e, "Unable to restore the source file"))?;
+            } else if staged.exists() {
+                fs::remove_file(&staged).map_err(|e| utils::treat(e, "Unable to clean up the import"))?;
+            }
+            conn.execute("DELETE FROM import_move WHERE import_id = ?1 AND target = ?2", params![self.id, pending.target])
+                .map_err(|e| utils::treat(e, "Unable to record the import"))?;
+        }
+
+        Ok(())
+    }
+
+    fn record<T>(&self, conn: &mut Connection, write: impl FnOnce(&Transaction) -> Result<T, String>) -> Result<T, String> {
+        let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+        let value = write(&tx)?;
+
+        tx.execute("UPDATE import_journal SET state = 'committed' WHERE id = ?1", params![self.id])
+            .map_err(|e| utils::treat(e, "Unable to commit the import"))?;
+        tx.commit().map_err(|e| utils::treat(e, "Unable to commit transaction"))?;
+
+        Ok(value)
+    }
+
+    fn publish(&self, conn: &Connection) -> Result<Vec<UnremovedSource>, String> {
+        for dir in STAGED_DIRS {
+            let target_dir = self.library_root.join(dir);
+            fs::create_dir_all(&target_dir).map_err(|e| utils::treat(e, "Unable to create required directory"))?;
+
+            let Ok(entries) = fs::read_dir(self.root.join(dir)) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                fs::rename(entry.path(), target_dir.join(entry.file_name())).map_err(|e| utils::treat(e, "Unable to move the imported files into the library"))?;
+            }
+        }
+
+        let mut unremoved = Vec::new();
+        for pending in self.moves(conn)? {
+            let source = Path::new(&pending.source);
+            if pending.renamed || !source.exists() {
+                continue;
+            }
+
+            let reason = if !self.library_root.join("originals").join(&pending.target).exists() {
+                utils::treat_msg("The imported file is missing from the library")
+            } else {
+                match fs::remove_file(source) {
+                    Ok(()) => continue,
+                    Err(e) => utils::treat(e, "Unable to remove the source file"),
+                }
+            };
+            unremoved.push(UnremovedSource { path: pending.source, reason });
+        }
+
+        Ok(unremoved)
+    }
+
+    fn finish(&self, conn: &Connection) -> Result<(), String> {
+        conn.execute("DELETE FROM import_move WHERE import_id = ?1", params![self.id])
+            .map_err(|e| utils::treat(e, "Unable to finish the import"))?;
+        conn.execute("DELETE FROM import_journal WHERE id = ?1", params![self.id])
+            .map_err(|e| utils::treat(e, "Unable to finish the import"))?;
+        if self.root.exists() {
+            fs::remove_dir_all(&self.root).map_err(|e| utils::treat(e, "Unable to clean up the import"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ImportStage {
+    fn drop(&mut self) {
+        ACTIVE_IMPORTS.lock().unwrap_or_else(PoisonError::into_inner).retain(|id| *id != self.id);
+    }
+}
+
+pub fn recover_imports(conn: &Connection, library_root: &Path) -> Result<(), String> {
+    let staging_root = library_root.join(STAGING_DIR);
+    let mut stmt = conn.prepare("SELECT id, state FROM import_journal").map_err(|e| utils::treat(e, "Unable to read the import journal"))?;
+    let imports = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| utils::treat(e, "Unable to read the import journal"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| utils::treat(e, "Unable to read the import journal"))?;
+
+    let mut kept = ACTIVE_IMPORTS.lock().unwrap_or_else(PoisonError::into_inner).clone();
+    for (id, state) in imports {
+        if kept.contains(&id) {
+            continue;
+        }
+        let stage = ImportStage::open(library_root, id.clone());
+        if state == "committed" {
+            for unremoved in stage.publish(conn)? {
+                log::warn!("Unable to remove the imported source {}: {}", unremoved.path, unremoved.reason);
+            }
+        } else if let Err(e) = stage.restore_moves(conn, None) {
+            log::warn!("Unable to roll back the import {}: {}", id, e);
+            kept.push(id);
+            continue;
+        }
+        stage.finish(conn)?;
+    }
+
+    if let Ok(entries) = fs::read_dir(&staging_root) {
+        for entry in entries.flatten().filter(|entry| !kept.iter().any(|id| entry.file_name() == id.as_str())) {
+            fs::remove_dir_all(entry.path()).map_err(|e| utils::treat(e, "Unable to clean up the import"))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::schema;
+
+    fn library(name: &str) -> (Connection, PathBuf) {
+        let root = std::env::temp_dir().join(format!("staging-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        schema::base_library().execute("VACUUM INTO ?1", params![root.join("lib.db").to_string_lossy()]).unwrap();
+        let conn = Connection::open(root.join("lib.db")).unwrap();
+        schema::migrate(&conn).unwrap();
+        (conn, root)
+    }
+
+    fn journal_ids(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn.prepare("SELECT id FROM import_journal").unwrap();
+        stmt.query_map([], |row| row.get(0)).unwrap().collect::<Result<Vec<String>, _>>().unwrap()
+    }
+
+    #[test]
+    fn failed_commits_are_aborted() {
+        let (mut conn, root) = library("abort");
+        conn.execute_batch("CREATE TRIGGER reject_commit BEFORE UPDATE ON import_journal BEGIN SELECT RAISE(ABORT, 'rejected'); END").unwrap();
+        let source = root.join("source.jpg");
+        fs::write(&source, b"photo").unwrap();
+
+        let stage = ImportStage::begin(&conn, &root).unwrap();
+        let stage_root = stage.root().to_path_buf();
+        assert_eq!(stage.stage_move(&source, "target.jpg").unwrap(), (hash::hash_bytes(b"photo", hash::CURRENT_ALGORITHM), 5));
+        assert!(!source.exists());
+
+        assert!(stage.commit(&mut conn, |_| Ok(())).is_err());
+        assert_eq!(fs::read(&source).unwrap(), b"photo");
+        assert!(journal_ids(&conn).is_empty());
+        assert!(!stage_root.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn recovery_skips_active_imports() {
+        let (conn, root) = library("recover");
+        let stage = ImportStage::begin(&conn, &root).unwrap();
+
+        let stale = ImportStage::begin(&conn, &root).unwrap();
+        let stale_root = stale.root().to_path_buf();
+        drop(stale);
+
+        recover_imports(&conn, &root).unwrap();
+        assert_eq!(journal_ids(&conn), vec![stage.id.clone()]);
+        assert!(stage.root().exists());
+        assert!(!stale_root.exists());
+
+        stage.abort(&conn).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn moves_are_renamed_and_restored_on_abort() {
+        let (mut conn, root) = library("publish");
+        let source = root.join("source.jpg");
+
+        for commit in [false, true] {
+            fs::write(&source, b"photo").unwrap();
+            let stage = ImportStage::begin(&conn, &root).unwrap();
+            stage.stage_move(&source, "target.jpg").unwrap();
+            assert!(!source.exists());
+            assert!(stage.dir("originals").join("target.jpg").exists());
+
+            if commit {
+                let (_, unremoved) = stage.commit(&mut conn, |_| Ok(())).unwrap();
+                assert!(unremoved.is_empty());
+            } else {
+                stage.abort(&conn).unwrap();
+            }
+            assert_eq!(source.exists(), !commit);
+            assert_eq!(root.join("originals").join("target.jpg").exists(), commit);
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn interrupted_moves_are_put_back_by_recovery() {
+        let (conn, root) = library("interrupted");
+        let source = root.join("source.jpg");
+        fs::write(&source, b"photo").unwrap();
+
+        let stage = ImportStage::begin(&conn, &root).unwrap();
+        stage.stage_move(&source, "target.jpg").unwrap();
+        drop(stage);
+
+        recover_imports(&conn, &root).unwrap();
+        assert_eq!(fs::read(&source).unwrap(), b"photo");
+        assert!(journal_ids(&conn).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
\ No newline at end of file
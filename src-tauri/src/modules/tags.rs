@@ -0,0 +1,417 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::modules::journal;
+use crate::modules::library;
+use crate::modules::sidecar;
+use crate::modules::utils;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+    pub parent: Option<String>,
+    pub path: String,
+    pub item_count: u32,
+}
+
+#[tauri::command]
+pub fn get_tags(app: tauri::AppHandle, library_id: String) -> Result<Vec<Tag>, String> {
+    let conn = library::get_db_connection(&app, &library_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT tag.id, tag.name, tag.parent, tag.path, COUNT(item_tag.item_id)
+        FROM tag
+        LEFT JOIN item_tag ON item_tag.tag_id = tag.id
+        GROUP BY tag.id
+        ORDER BY tag.path COLLATE NOCASE"
+    ).map_err(|e| utils::treat(e, "Unable to obtain tags"))?;
+
+    let tag_iter = stmt.query_map([], |row| {
+        Ok(Tag {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            parent: row.get(2)?,
+            path: row.get(3)?,
+            item_count: row.get(4)?,
+        })
+    }).map_err(|e| utils::treat(e, "Unable to obtain tags"))?;
+
+    let mut tags = Vec::new();
+    for tag in tag_iter {
+        tags.push(tag.map_err(|e| utils::treat(e, "Unable to obtain tags"))?);
+    }
+
+    Ok(tags)
+}
+
+#[tauri::command]
+pub fn create_tag(app: tauri::AppHandle, library_id: String, path: String) -> Result<String, String> {
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let tag_id = ensure_tag_path(&tx, &path)?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save the tag"))?;
+    Ok(tag_id)
+}
+
+#[tauri::command]
+pub fn rename_tag(app: tauri::AppHandle, library_id: String, tag_id: String, name: String) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains('/') {
+        return Err(utils::treat_msg("Tag names cannot be empty or contain \"/\""));
+    }
+
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let (parent, old_path) = get_tag_location(&tx, &tag_id)?;
+    if has_sibling_named(&tx, parent.as_deref(), name, &tag_id)? {
+        return Err(utils::treat_msg("A tag with this name already exists"));
+    }
+    let new_path = match &parent {
+        Some(parent) => format!("{}/{}", get_tag_location(&tx, parent)?.1, name),
+        None => name.to_string(),
+    };
+
+    let item_ids = get_tagged_item_ids(&tx, &old_path)?;
+    let snapshot = journal::capture(&tx, "tag", "id", &get_tag_tree_ids(&tx, &old_path)?)?;
+    tx.execute("UPDATE tag SET name = ?1 WHERE id = ?2", params![name, tag_id]).map_err(|e| utils::treat(e, "Unable to rename the tag"))?;
+    move_tag_paths(&tx, &old_path, &new_path)?;
+    journal::record(&tx, "rename_tag", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to rename the tag"))?;
+    sidecar::sync_items(&app, &library_id, &item_ids)
+}
+
+#[tauri::command]
+pub fn merge_tags(app: tauri::AppHandle, library_id: String, source_ids: Vec<String>, target_id: String) -> Result<(), String> {
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let (_, target_path) = get_tag_location(&tx, &target_id)?;
+    let mut item_ids = Vec::new();
+    let mut tag_ids = Vec::new();
+    let mut sources = Vec::new();
+
+    for source_id in source_ids.iter().filter(|id| **id != target_id) {
+        let Some((_, source_path)) = find_tag_location(&tx, source_id)? else {
+            continue;
+        };
+        if target_path.starts_with(&format!("{}/", source_path)) {
+            return Err(utils::treat_msg("A tag cannot be merged into one of its own children"));
+        }
+
+        item_ids.extend(get_tagged_item_ids(&tx, &source_path)?);
+        tag_ids.extend(get_tag_tree_ids(&tx, &source_path)?);
+        sources.push(source_id);
+    }
+
+    item_ids.sort();
+    item_ids.dedup();
+    let snapshots = vec![journal::capture(&tx, "tag", "id", &tag_ids)?, journal::capture(&tx, "item_tag", "item_id", &item_ids)?];
+    for source_id in sources {
+        if find_tag_location(&tx, source_id)?.is_some() {
+            merge_tag(&tx, source_id, &target_id)?;
+        }
+    }
+
+    journal::record(&tx, "merge_tags", snapshots)?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to merge the tags"))?;
+    sidecar::sync_items(&app, &library_id, &item_ids)
+}
+
+#[tauri::command]
+pub fn delete_tag(app: tauri::AppHandle, library_id: String, tag_id: String) -> Result<(), String> {
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let (_, path) = get_tag_location(&tx, &tag_id)?;
+    let item_ids = get_tagged_item_ids(&tx, &path)?;
+    let snapshots = vec![journal::capture(&tx, "tag", "id", &get_tag_tree_ids(&tx, &path)?)?, journal::capture(&tx, "item_tag", "item_id", &item_ids)?];
+
+    delete_tag_tree(&tx, &path)?;
+
+    journal::record(&tx, "delete_tag", snapshots)?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to delete the tag"))?;
+    sidecar::sync_items(&app, &library_id, &item_ids)
+}
+
+#[tauri::command]
+pub fn tag_items(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, tag_id: String) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item_tag", "item_id", &item_ids)?;
+    let now = Utc::now().to_rfc3339();
+    for item_id in &item_ids {
+        tx.execute(
+            "INSERT OR IGNORE INTO item_tag (item_id, tag_id, added_at) VALUES (?1, ?2, ?3)",
+            params![item_id, tag_id, now],
+        ).map_err(|e| utils::treat(e, "Unable to tag the item"))?;
+    }
+    journal::record(&tx, "tag_items", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save tagged items"))?;
+    sidecar::sync_items(&app, &library_id, &item_ids)
+}
+
+#[tauri::command]
+pub fn untag_items(app: tauri::AppHandle, library_id: String, item_ids: Vec<String>, tag_id: String) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = library::get_db_connection(&app, &library_id)?;
+    let tx = conn.transaction().map_err(|e| utils::treat(e, "Unable to begin transaction"))?;
+    let snapshot = journal::capture(&tx, "item_tag", "item_id", &item_ids)?;
+    for item_id in &item_ids {
+        tx.execute(
+            "DELETE FROM item_tag WHERE item_id = ?1 AND tag_id = ?2",
+            params![item_id, tag_id],
+        ).map_err(|e| utils::treat(e, "Unable to untag the item"))?;
+    }
+    journal::record(&tx, "untag_items", vec![snapshot])?;
+    tx.commit().map_err(|e| utils::treat(e, "Unable to save untagged items"))?;
+    sidecar::sync_items(&app, &library_id, &item_ids)
+}
+
+pub fn get_item_tag_paths(conn: &Connection, item_id: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT tag.path FROM tag JOIN item_tag ON item_tag.tag_id = tag.id WHERE item_tag.item_id = ?1 ORDER BY tag.path")
+        .map_err(|e| utils::treat(e, "Unable to obtain tags"))?;
+    let path_iter = stmt.query_map(params![item_id], |row| row.get(0)).map_err(|e| utils::treat(e, "Unable to obtain tags"))?;
+
+    let mut paths = Vec::new();
+    for path in path_iter {
+        paths.push(path.map_err(|e| utils::treat(e, "Unable to obtain tags"))?);
+    }
+
+    Ok(paths)
+}
+
+fn get_tag_tree_ids(tx: &Transaction, path: &str) -> Result<Vec<String>, String> {
+    let mut stmt = tx
+        .prepare(&format!("SELECT id FROM tag WHERE {}", path_condition("path", "?1")))
+        .map_err(|e| utils::treat(e, "Unable to obtain tags"))?;
+    stmt.query_map(params![path], |row| row.get(0))
+        .and_then(|rows| rows.collect::<Result<Vec<String>, _>>())
+        .map_err(|e| utils::treat(e, "Unable to obtain tags"))
+}
+
+fn get_tagged_item_ids(tx: &Transaction, path: &str) -> Result<Vec<String>, String> {
+    let mut stmt = tx
+        .prepare(&format!("SELECT DISTINCT item_tag.item_id FROM item_tag JOIN tag ON tag.id = item_tag.tag_id WHERE {}", path_condition("tag.path", "?1")))
+        .map_err(|e| utils::treat(e, "Unable to obtain tags"))?;
+    let id_iter = stmt.query_map(params![path], |row| row.get(0)).map_err(|e| utils::treat(e, "Unable to obtain tags"))?;
+
+    let mut item_ids = Vec::new();
+    for item_id in id_iter {
+        item_ids.push(item_id.map_err(|e| utils::treat(e, "Unable to obtain tags"))?);
+    }
+
+    Ok(item_ids)
+}
+
+pub fn ensure_tag_path(tx: &Transaction, path: &str) -> Result<String, String> {
+    let mut parent: Option<String> = None;
+    let mut current_path = String::new();
+
+    for name in path.split('/').map(str::trim).filter(|name| !name.is_empty()) {
+        current_path = if current_path.is_empty() { name.to_string() } else { format!("{}/{}", current_path, name) };
+
+        let existing: Option<String> = tx
+            .query_row("SELECT id FROM tag WHERE name = ?1 AND parent IS ?2", params![name, parent], |row| row.get(0))
+            .optional()
+            .map_err(|e| utils::treat(e, "Unable to obtain tags"))?;
+
+        let tag_id = match existing {
+            Some(tag_id) => tag_id,
+            None => {
+                let tag_id = Uuid::new_v4().to_string();
+                tx.execute(
+                    "INSERT INTO tag (id, name, parent, path, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![tag_id, name, parent, current_path, Utc::now().to_rfc3339()],
+                ).map_err(|e| utils::treat(e, "Unable to create the tag"))?;
+                tag_id
+            }
+        };
+
+        parent = Some(tag_id);
+    }
+
+    parent.ok_or_else(|| utils::treat_msg("Tag names cannot be empty"))
+}
+
+pub fn path_condition(path: &str, root: &str) -> String {
+    format!("({path} = {root} OR substr({path}, 1, length({root}) + 1) = {root} || '/')")
+}
+
+fn get_tag_location(tx: &Transaction, tag_id: &str) -> Result<(Option<String>, String), String> {
+    find_tag_location(tx, tag_id)?.ok_or_else(|| utils::treat_msg("Tag not found"))
+}
+
+fn find_tag_location(tx: &Transaction, tag_id: &str) -> Result<Option<(Option<String>, String)>, String> {
+    tx.query_row("SELECT parent, path FROM tag WHERE id = ?1", params![tag_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()
+        .map_err(|e| utils::treat(e, "Unable to obtain tags"))
+}
+
+fn has_sibling_named(tx: &Transaction, parent: Option<&str>, name: &str, tag_id: &str) -> Result<bool, String> {
+    tx.query_row("SELECT EXISTS (SELECT 1 FROM tag WHERE parent IS ?1 AND name = ?2 AND id != ?3)", params![parent, name, tag_id], |row| row.get(0))
+        .map_err(|e| utils::treat(e, "Unable to obtain tags"))
+}
+
+fn move_tag_paths(tx: &Transaction, old_path: &str, new_path: &str) -> Result<(), String> {
+    tx.execute(
+        &format!("UPDATE tag SET path = ?2 || substr(path, length(?1) + 1) WHERE {}", path_condition("path", "?1")),
+        params![old_path, new_path],
+    ).map_err(|e| utils::treat(e, "Unable to update the tag hierarchy"))?;
+    Ok(())
+}
+
+fn delete_tag_tree(tx: &Transaction, path: &str) -> Result<(), String> {
+    tx.execute(
+        &format!("DELETE FROM item_tag WHERE tag_id IN (SELECT id FROM tag WHERE {})", path_condition("path", "?1")),
+        params![path],
+    ).map_err(|e| utils::treat(e, "Unable to delete the tag"))?;
+    tx.execute(&format!("DELETE FROM tag WHERE {}", path_condition("path", "?1")), params![path])
+        .map_err(|e| utils::treat(e, "Unable to delete the tag"))?;
+    Ok(())
+}
+
+fn merge_tag(tx: &Transaction, source_id: &str, target_id: &str) -> Result<(), String> {
+    let (_, target_path) = get_tag_location(tx, target_id)?;
+    tx.execute(
+        "INSERT OR IGNORE INTO item_tag (item_id, tag_id, added_at) SELECT item_id, ?1, added_at FROM item_tag WHERE tag_id = ?2",
+        params![target_id, source_id],
+    ).map_err(|e| utils::treat(e, "Unable to merge the tags"))?;
+    tx.execute("DELETE FROM item_tag WHERE tag_id = ?1", params![source_id]).map_err(|e| utils::treat(e, "Unable to merge the tags"))?;
+
+    let mut stmt = tx.prepare("SELECT id, name, path FROM tag WHERE parent = ?1").map_err(|e| utils::treat(e, "Unable to obtain tags"))?;
+    let children = stmt
+        .query_map(params![source_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+        .map_err(|e| utils::treat(e, "Unable to obtain tags"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| utils::treat(e, "Unable to obtain tags"))?;
+
+    for (child_id, name, child_path) in children {
+        let sibling: Option<String> = tx
+            .query_row("SELECT id FROM tag WHERE parent = ?1 AND name = ?2", params![target_id, name], |row| row.get(0))
+            .optional()
+            .map_err(|e| utils::treat(e, "Unable to obtain tags"))?;
+
+        match sibling {
+            Some(sibling_id) => merge_tag(tx, &child_id, &sibling_id)?,
+            None => {
+                tx.execute("UPDATE tag SET parent = ?1 WHERE id = ?2", params![target_id, child_id]).map_err(|e| utils::treat(e, "Unable to merge the tags"))?;
+                move_tag_paths(tx, &child_path, &format!("{}/{}", target_path, name))?;
+            }
+        }
+    }
+
+    tx.execute("DELETE FROM tag WHERE id = ?1", params![source_id]).map_err(|e| utils::treat(e, "Unable to merge the tags"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE tag (id TEXT PRIMARY KEY, name TEXT NOT NULL, parent TEXT, path TEXT NOT NULL, created_at TEXT NOT NULL);
+            CREATE TABLE item_tag (item_id TEXT NOT NULL, tag_id TEXT NOT NULL, added_at TEXT NOT NULL, PRIMARY KEY (item_id, tag_id));",
+        ).unwrap();
+        conn
+    }
+
+    fn tag(tx: &Transaction, path: &str, item_id: &str) -> String {
+        let tag_id = ensure_tag_path(tx, path).unwrap();
+        tx.execute("INSERT INTO item_tag (item_id, tag_id, added_at) VALUES (?1, ?2, '')", params![item_id, tag_id]).unwrap();
+        tag_id
+    }
+
+    fn paths(tx: &Transaction) -> Vec<String> {
+        let mut stmt = tx.prepare("SELECT path FROM tag ORDER BY path").unwrap();
+        stmt.query_map([], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap()
+    }
+
+    fn sorted(mut values: Vec<String>) -> Vec<String> {
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn hierarchy_matching_is_exact() {
+        let mut conn = library();
+        let tx = conn.transaction().unwrap();
+        tag(&tx, "Travel", "1");
+        tag(&tx, "Travel/Japan", "2");
+        tag(&tx, "travel/Beach", "3");
+        tag(&tx, "Traveling", "4");
+        tag(&tx, "Tr_vel/Kyoto", "5");
+        tag(&tx, "Tr%", "6");
+
+        assert_eq!(sorted(get_tagged_item_ids(&tx, "Travel").unwrap()), ["1", "2"]);
+        assert_eq!(get_tagged_item_ids(&tx, "Tr_vel").unwrap(), ["5"]);
+        assert_eq!(get_tagged_item_ids(&tx, "Tr%").unwrap(), ["6"]);
+    }
+
+    #[test]
+    fn deleting_a_tag_keeps_lookalikes() {
+        let mut conn = library();
+        let tx = conn.transaction().unwrap();
+        tag(&tx, "Travel/Japan", "1");
+        tag(&tx, "travel/Beach", "2");
+        tag(&tx, "Tr_vel", "3");
+
+        delete_tag_tree(&tx, "Travel").unwrap();
+        assert_eq!(paths(&tx), ["Tr_vel", "travel", "travel/Beach"]);
+        assert_eq!(get_tagged_item_ids(&tx, "travel").unwrap(), ["2"]);
+    }
+
+    #[test]
+    fn moving_a_tag_rewrites_only_its_subtree() {
+        let mut conn = library();
+        let tx = conn.transaction().unwrap();
+        tag(&tx, "Travel/Japan/Kyoto", "1");
+        tag(&tx, "Traveling", "2");
+
+        move_tag_paths(&tx, "Travel", "Trips").unwrap();
+        assert_eq!(paths(&tx), ["Traveling", "Trips", "Trips/Japan", "Trips/Japan/Kyoto"]);
+    }
+
+    #[test]
+    fn renaming_detects_sibling_collisions() {
+        let mut conn = library();
+        let tx = conn.transaction().unwrap();
+        let kyoto = tag(&tx, "Japan/Kyoto", "1");
+        let japan = ensure_tag_path(&tx, "Japan").unwrap();
+        tag(&tx, "Japan/Osaka", "2");
+        tag(&tx, "Trips/Kyoto", "3");
+
+        assert!(has_sibling_named(&tx, Some(&japan), "Osaka", &kyoto).unwrap());
+        assert!(!has_sibling_named(&tx, Some(&japan), "Kyoto", &kyoto).unwrap());
+        assert!(!has_sibling_named(&tx, Some(&japan), "Nara", &kyoto).unwrap());
+        assert!(has_sibling_named(&tx, None, "Trips", &japan).unwrap());
+    }
+
+    #[test]
+    fn merging_combines_children_with_existing_siblings() {
+        let mut conn = library();
+        let tx = conn.transaction().unwrap();
+        tag(&tx, "Japan/Kyoto/Gion", "1");
+        tag(&tx, "Japan/Osaka", "2");
+        let target = tag(&tx, "Trips/Kyoto", "3");
+        let source = ensure_tag_path(&tx, "Japan").unwrap();
+        let trips = ensure_tag_path(&tx, "Trips").unwrap();
+        assert_ne!(target, trips);
+
+        merge_tag(&tx, &source, &trips).unwrap();
+        assert_eq!(paths(&tx), ["Trips", "Trips/Kyoto", "Trips/Kyoto/Gion", "Trips/Osaka"]);
+        assert_eq!(sorted(get_tagged_item_ids(&tx, "Trips/Kyoto").unwrap()), ["1", "3"]);
+
+        let kyoto_count: u32 = tx.query_row("SELECT COUNT(*) FROM tag WHERE name = 'Kyoto'", [], |row| row.get(0)).unwrap();
+        assert_eq!(kyoto_count, 1);
+    }
+}
@@ -1,11 +1,22 @@
 use chrono::{DateTime, Utc};
-use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+use image::codecs::gif::GifDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Frame, ImageDecoder, ImageReader};
+use jxl_oxide::integration::JxlDecoder;
+use libheif_rs::{ColorSpace, CompressionFormat, HeifContext, LibHeif, RgbChroma, TransferCharacteristics};
 use log;
 use rusqlite::Row;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek};
+use std::path::Path;
 
+use crate::modules::color::{self, HdrInfo, HdrTransfer};
+use crate::modules::edits::EditOperation;
+use crate::modules::hash::ChecksumAlgorithm;
 use crate::modules::utils;
+use crate::modules::xmp::Crop;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Item {
@@ -16,13 +27,58 @@ pub struct Item {
     pub width: u32,
     pub height: u32,
     pub checksum: String,
+    #[serde(default)]
+    pub checksum_algorithm: ChecksumAlgorithm,
     pub is_favorite: bool,
     pub is_screenshot: bool,
     pub is_screen_recording: bool,
     pub live_video: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub page_count: u32,
+    pub is_animated: bool,
+    pub frame_count: u32,
+    pub duration: Option<u32>,
+    pub color_space: Option<String>,
+    pub is_hdr: bool,
+    pub has_gain_map: bool,
+    pub bit_depth: u8,
+    pub camera_model: Option<String>,
+    pub place_name: Option<String>,
+    pub rating: u8,
+    pub color_label: Option<String>,
+    pub flag: i8,
+    pub title: Option<String>,
+    pub caption: Option<String>,
+    pub notes: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub crop: Option<Crop>,
+    pub time_zone: Option<i32>,
+    pub original_created_at: Option<DateTime<Utc>>,
+    pub original_time_zone: Option<i32>,
+    pub edits: Vec<EditOperation>,
+    pub source_path: Option<String>,
+    #[serde(default)]
+    pub is_offline: bool,
 }
 
+pub struct Animation {
+    pub frames: Vec<Frame>,
+    pub duration: u32,
+}
+
+pub struct LoadedImage {
+    pub image: image::DynamicImage,
+    pub page_count: u32,
+    pub animation: Option<Animation>,
+    pub icc_profile: Option<Vec<u8>>,
+    pub hdr: HdrInfo,
+}
+
+const ANIMATION_PREVIEW_SIZE: u32 = 320;
+
+pub const COLOR_LABELS: [&str; 5] = ["Red", "Yellow", "Green", "Blue", "Purple"];
+
 pub fn treat<E: Display>(e: E, msg: &str) -> String {
     log::error!("{}: {}", msg, e);
     msg.to_string()
@@ -39,6 +95,10 @@ pub fn map_extension_to_mime(ext: &str) -> &'static str {
         "gif" => "image/gif",
         "webp" => "image/webp",
         "heic" | "heif" => "image/heic",
+        "avif" => "image/avif",
+        "tif" | "tiff" => "image/tiff",
+        "bmp" => "image/bmp",
+        "jxl" => "image/jxl",
         "mp4" => "video/mp4",
         "mov" => "video/quicktime",
         "avi" => "video/x-msvideo",
@@ -50,30 +110,277 @@ fn unable_to_load_image<E: std::fmt::Display>(e: E) -> String {
     utils::treat(e, "Unable to load image")
 }
 
-pub fn load_image(data: &Vec<u8>, ext: &str) -> Result<image::DynamicImage, String> {
+pub fn normalize_color_label(label: &str) -> Option<String> {
+    COLOR_LABELS.iter().find(|known| known.eq_ignore_ascii_case(label.trim())).map(|known| known.to_string())
+}
+
+pub fn exif_string(exif: &exif::Exif, tag: exif::Tag) -> Option<String> {
+    match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+        exif::Value::Ascii(values) => values
+            .first()
+            .map(|value| String::from_utf8_lossy(value).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string())
+            .filter(|value| !value.is_empty()),
+        _ => None,
+    }
+}
+
+pub fn exif_orientation(exif: &exif::Exif) -> u32 {
+    exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY).and_then(|field| field.value.get_uint(0)).unwrap_or(1)
+}
+
+pub fn apply_orientation(image: image::DynamicImage, orientation: u32) -> image::DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+pub fn file_extension(file_name: &str) -> &str {
+    std::path::Path::new(file_name).extension().and_then(|ext| ext.to_str()).unwrap_or("")
+}
+
+pub fn original_file_name(item: &Item) -> String {
+    format!("{}.{}", item.id, file_extension(&item.original_name))
+}
+
+pub fn original_path(library_root: &std::path::Path, item: &Item) -> std::path::PathBuf {
+    match &item.source_path {
+        Some(source_path) => std::path::PathBuf::from(source_path),
+        None => library_root.join("originals").join(original_file_name(item)),
+    }
+}
+
+pub fn is_supported_extension(ext: &str) -> bool {
+    matches!(
+        ext.to_lowercase().as_str(),
+        "jpg" | "jpeg" | "png" | "webp" | "gif" | "bmp" | "tif" | "tiff" | "heic" | "heif" | "avif" | "jxl"
+    )
+}
+
+pub fn unsupported_file_type(ext: &str) -> String {
+    if ext.is_empty() {
+        "Files without an extension are not supported".to_string()
+    } else {
+        format!("The .{} file type is not supported", ext.to_lowercase())
+    }
+}
+
+pub fn load_image(data: &[u8], ext: &str) -> Result<LoadedImage, String> {
+    let heif = || HeifContext::read_from_bytes(data).map_err(unable_to_load_image);
+    load_image_from(&mut Cursor::new(data), ext, heif, || color::has_jpeg_gain_map(data))
+}
+
+pub fn load_image_file(path: &Path, ext: &str) -> Result<LoadedImage, String> {
+    let mut reader = BufReader::new(File::open(path).map_err(|e| utils::treat(e, "Unable to read photo data"))?);
+    let heif = || HeifContext::read_from_file(&path.to_string_lossy()).map_err(unable_to_load_image);
+    load_image_from(&mut reader, ext, heif, || file_contains(path, color::GAIN_MAP_MARKERS))
+}
+
+fn load_image_from<'a, R: BufRead + Seek>(
+    reader: &mut R,
+    ext: &str,
+    heif: impl FnOnce() -> Result<HeifContext<'a>, String>,
+    has_gain_map: impl FnOnce() -> bool,
+) -> Result<LoadedImage, String> {
     match ext.to_lowercase().as_str() {
-        "jpg" | "jpeg" | "png" | "webp" | "gif" => {
-            image::load_from_memory(data).map_err(unable_to_load_image)
+        "gif" | "webp" => {
+            if let Some((first_frame, animation)) = load_animation(&mut *reader, ext)? {
+                let image = image::DynamicImage::ImageRgba8(first_frame);
+                return Ok(LoadedImage { image, page_count: 1, animation: Some(animation), icc_profile: None, hdr: HdrInfo::sdr(8) });
+            }
+
+            reader.rewind().map_err(unable_to_load_image)?;
+            let (image, icc_profile) = decode_with_profile(reader)?;
+            let hdr = HdrInfo::sdr(color::bit_depth(&image));
+            Ok(LoadedImage { image, page_count: 1, animation: None, icc_profile, hdr })
+        }
+        "jpg" | "jpeg" => {
+            let (image, icc_profile) = decode_with_profile(reader)?;
+            let hdr = HdrInfo { has_gain_map: has_gain_map(), ..HdrInfo::sdr(8) };
+            Ok(LoadedImage { image, page_count: 1, animation: None, icc_profile, hdr })
+        }
+        "png" | "bmp" => {
+            let (image, icc_profile) = decode_with_profile(reader)?;
+            let hdr = HdrInfo::sdr(color::bit_depth(&image));
+            Ok(LoadedImage { image, page_count: 1, animation: None, icc_profile, hdr })
         }
-        "heic" | "heif" => {
-            let lib_heif = LibHeif::new();
-            let ctx = HeifContext::read_from_bytes(data).map_err(unable_to_load_image)?;
-            let handle = ctx.primary_image_handle().map_err(unable_to_load_image)?;
-            let image = lib_heif.decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None).map_err(unable_to_load_image)?;
-            let width = image.width();
-            let height = image.height();
-
-            let plane = image.planes().interleaved.ok_or_else(|| utils::treat_msg("Unable to load image"))?;
-            let data = plane.data.to_vec();
-
-            image::RgbImage::from_raw(width, height, data)
-                .map(image::DynamicImage::ImageRgb8)
-                .ok_or_else(|| utils::treat_msg("Unable to load image"))
+        "tif" | "tiff" => {
+            let (image, icc_profile) = decode_with_profile(&mut *reader)?;
+            reader.rewind().map_err(unable_to_load_image)?;
+            let page_count = count_tiff_pages(reader).unwrap_or(1);
+            let hdr = HdrInfo::sdr(color::bit_depth(&image));
+            Ok(LoadedImage { image, page_count, animation: None, icc_profile, hdr })
         }
-        _ => Err("File type not supported".to_string())
+        "jxl" => {
+            let mut decoder = JxlDecoder::new(reader).map_err(unable_to_load_image)?;
+            let icc_profile = decoder.icc_profile().unwrap_or(None);
+            let image = image::DynamicImage::from_decoder(decoder).map_err(unable_to_load_image)?;
+            let hdr = HdrInfo::sdr(color::bit_depth(&image));
+            Ok(LoadedImage { image, page_count: 1, animation: None, icc_profile, hdr })
+        }
+        "avif" if !has_av1_decoder() => Err(utils::treat_msg("AVIF images require libheif to be built with an AV1 decoder (dav1d or libaom)")),
+        "heic" | "heif" | "avif" => load_heif(heif()?),
+        _ => Err(unsupported_file_type(ext))
     }
 }
 
+pub fn decoded_size(path: &Path, ext: &str) -> Option<u64> {
+    match ext.to_lowercase().as_str() {
+        "heic" | "heif" | "avif" => {
+            let ctx = HeifContext::read_from_file(path.to_str()?).ok()?;
+            let handle = ctx.primary_image_handle().ok()?;
+            let channel_bytes = if handle.luma_bits_per_pixel() > 8 { 6 } else { 3 };
+            Some(2 * handle.width() as u64 * handle.height() as u64 * channel_bytes)
+        }
+        "jxl" => Some(JxlDecoder::new(BufReader::new(File::open(path).ok()?)).ok()?.total_bytes()),
+        _ => Some(ImageReader::open(path).ok()?.with_guessed_format().ok()?.into_decoder().ok()?.total_bytes()),
+    }
+}
+
+pub fn file_contains(path: &Path, needles: &[&[u8]]) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    let mut reader = BufReader::new(file);
+    let overlap = needles.iter().map(|needle| needle.len()).max().unwrap_or(1) - 1;
+    let mut window = Vec::new();
+    let mut chunk = vec![0; 64 * 1024];
+
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) | Err(_) => return false,
+            Ok(read) => read,
+        };
+        window.extend_from_slice(&chunk[..read]);
+        if needles.iter().any(|needle| window.windows(needle.len()).any(|candidate| candidate == *needle)) {
+            return true;
+        }
+        window.drain(..window.len().saturating_sub(overlap));
+    }
+}
+
+fn has_av1_decoder() -> bool {
+    !LibHeif::new().decoder_descriptors(1, Some(CompressionFormat::Av1)).is_empty()
+}
+
+fn load_heif(ctx: HeifContext) -> Result<LoadedImage, String> {
+    let lib_heif = LibHeif::new();
+    let handle = ctx.primary_image_handle().map_err(unable_to_load_image)?;
+    let icc_profile = handle.color_profile_raw().map(|profile| profile.data);
+    let hdr = HdrInfo {
+        transfer: handle.color_profile_nclx().and_then(|nclx| match nclx.transfer_characteristics() {
+            TransferCharacteristics::ITU_R_BT_2100_0_PQ => Some(HdrTransfer::Pq),
+            TransferCharacteristics::ITU_R_BT_2100_0_HLG => Some(HdrTransfer::Hlg),
+            _ => None,
+        }),
+        has_gain_map: handle
+            .auxiliary_images(None)
+            .iter()
+            .any(|aux| aux.auxiliary_type().is_ok_and(|aux_type| aux_type.contains("gainmap"))),
+        bit_depth: handle.luma_bits_per_pixel(),
+    };
+
+    let chroma = if hdr.bit_depth > 8 { RgbChroma::HdrRgbLe } else { RgbChroma::Rgb };
+    let decoded = lib_heif.decode(&handle, ColorSpace::Rgb(chroma), None).map_err(unable_to_load_image)?;
+    let width = decoded.width();
+    let height = decoded.height();
+
+    let plane = decoded.planes().interleaved.ok_or_else(|| utils::treat_msg("Unable to load image"))?;
+    let rows = plane.data.chunks(plane.stride).take(height as usize);
+
+    let image = if hdr.bit_depth > 8 {
+        let max = ((1u32 << plane.bits_per_pixel) - 1) as f32;
+        let pixels: Vec<u16> = rows
+            .flat_map(|row| row[..width as usize * 6].chunks_exact(2))
+            .map(|sample| (u16::from_le_bytes([sample[0], sample[1]]) as f32 / max * 65535.0).round() as u16)
+            .collect();
+
+        image::ImageBuffer::from_raw(width, height, pixels).map(image::DynamicImage::ImageRgb16)
+    } else {
+        let pixels: Vec<u8> = rows.flat_map(|row| row[..width as usize * 3].iter().copied()).collect();
+
+        image::RgbImage::from_raw(width, height, pixels).map(image::DynamicImage::ImageRgb8)
+    };
+
+    image
+        .map(|image| LoadedImage { image, page_count: 1, animation: None, icc_profile, hdr })
+        .ok_or_else(|| utils::treat_msg("Unable to load image"))
+}
+
+fn decode_with_profile<R: BufRead + Seek>(reader: R) -> Result<(image::DynamicImage, Option<Vec<u8>>), String> {
+    let mut decoder = ImageReader::new(reader)
+        .with_guessed_format()
+        .map_err(unable_to_load_image)?
+        .into_decoder()
+        .map_err(unable_to_load_image)?;
+    let icc_profile = decoder.icc_profile().unwrap_or(None);
+    let image = image::DynamicImage::from_decoder(decoder).map_err(unable_to_load_image)?;
+
+    Ok((image, icc_profile))
+}
+
+fn load_animation<R: BufRead + Seek>(reader: R, ext: &str) -> Result<Option<(image::RgbaImage, Animation)>, String> {
+    let frames = if ext.eq_ignore_ascii_case("gif") {
+        GifDecoder::new(reader).map_err(unable_to_load_image)?.into_frames()
+    } else {
+        let decoder = WebPDecoder::new(reader).map_err(unable_to_load_image)?;
+        if !decoder.has_animation() {
+            return Ok(None);
+        }
+        decoder.into_frames()
+    };
+
+    let mut first_frame = None;
+    let mut preview_frames = Vec::new();
+    let mut duration = 0;
+
+    for frame in frames {
+        let frame = frame.map_err(unable_to_load_image)?;
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        duration += numer / denom.max(1);
+        preview_frames.push(downscale_frame(&frame));
+
+        if first_frame.is_none() {
+            first_frame = Some(frame.into_buffer());
+        }
+    }
+
+    match first_frame {
+        Some(first_frame) if preview_frames.len() > 1 => Ok(Some((first_frame, Animation { frames: preview_frames, duration }))),
+        _ => Ok(None),
+    }
+}
+
+fn downscale_frame(frame: &Frame) -> Frame {
+    let (width, height) = frame.buffer().dimensions();
+    let scale = (ANIMATION_PREVIEW_SIZE as f32 / width.max(height) as f32).min(1.0);
+    let resized = image::imageops::thumbnail(
+        frame.buffer(),
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+    );
+
+    Frame::from_parts(resized, 0, 0, frame.delay())
+}
+
+fn count_tiff_pages<R: Read + Seek>(reader: R) -> Option<u32> {
+    let mut decoder = tiff::decoder::Decoder::new(reader).ok()?;
+    let mut pages = 1;
+
+    while decoder.more_images() {
+        decoder.next_image().ok()?;
+        pages += 1;
+    }
+
+    Some(pages)
+}
+
 pub fn deserialize_item(item: &Row<'_>) -> Result<Item, rusqlite::Error> {
     Ok(Item {
         id: item.get(0)?,
@@ -91,5 +398,35 @@ pub fn deserialize_item(item: &Row<'_>) -> Result<Item, rusqlite::Error> {
             .map_err(|_| {
                 rusqlite::Error::InvalidColumnType(12, "created_at".to_string(), rusqlite::types::Type::Text)
             })?.with_timezone(&Utc),
+        page_count: item.get(12)?,
+        is_animated: item.get::<_, i32>(13)? != 0,
+        frame_count: item.get(14)?,
+        duration: item.get::<_, Option<u32>>(15)?,
+        color_space: item.get::<_, Option<String>>(16)?,
+        is_hdr: item.get::<_, i32>(17)? != 0,
+        has_gain_map: item.get::<_, i32>(18)? != 0,
+        bit_depth: item.get(19)?,
+        camera_model: item.get::<_, Option<String>>(20)?,
+        place_name: item.get::<_, Option<String>>(21)?,
+        color_label: item.get::<_, Option<String>>(22)?,
+        rating: item.get(23)?,
+        flag: item.get(24)?,
+        title: item.get::<_, Option<String>>(25)?,
+        caption: item.get::<_, Option<String>>(26)?,
+        notes: item.get::<_, Option<String>>(27)?,
+        latitude: item.get::<_, Option<f64>>(28)?,
+        longitude: item.get::<_, Option<f64>>(29)?,
+        crop: item.get::<_, Option<String>>(30)?.and_then(|crop| serde_json::from_str(&crop).ok()),
+        time_zone: item.get::<_, Option<i32>>(31)?,
+        original_created_at: item
+            .get::<_, Option<String>>(32)?
+            .map(|value| DateTime::parse_from_rfc3339(&value).map(|date| date.with_timezone(&Utc)))
+            .transpose()
+            .map_err(|_| rusqlite::Error::InvalidColumnType(32, "original_created_at".to_string(), rusqlite::types::Type::Text))?,
+        original_time_zone: item.get::<_, Option<i32>>(33)?,
+        edits: item.get::<_, Option<String>>(34)?.and_then(|edits| serde_json::from_str(&edits).ok()).unwrap_or_default(),
+        checksum_algorithm: ChecksumAlgorithm::from_name(&item.get::<_, Option<String>>(35)?.unwrap_or_default()),
+        source_path: item.get::<_, Option<String>>(36)?,
+        is_offline: item.get::<_, Option<i32>>(37)?.unwrap_or(0) != 0,
     })
 }
\ No newline at end of file
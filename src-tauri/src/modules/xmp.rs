@@ -0,0 +1,603 @@
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::ResolveResult;
+use quick_xml::{NsReader, Writer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::modules::utils;
+
+const NS_RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const NS_XMP: &str = "http://ns.adobe.com/xap/1.0/";
+const NS_DC: &str = "http://purl.org/dc/elements/1.1/";
+const NS_LR: &str = "http://ns.adobe.com/lightroom/1.0/";
+const NS_EXIF: &str = "http://ns.adobe.com/exif/1.0/";
+const NS_PHOTOSHOP: &str = "http://ns.adobe.com/photoshop/1.0/";
+const NS_CRS: &str = "http://ns.adobe.com/camera-raw-settings/1.0/";
+const PACKET_START: &[u8] = b"<x:xmpmeta";
+const PACKET_END: &[u8] = b"</x:xmpmeta>";
+const MAX_PACKET_SIZE: usize = 16 * 1024 * 1024;
+const PLACE_PROPERTIES: [&str; 3] = ["photoshop:City", "photoshop:State", "photoshop:Country"];
+const NAMESPACES: [(&str, &str); 7] = [("rdf", NS_RDF), ("xmp", NS_XMP), ("dc", NS_DC), ("lr", NS_LR), ("exif", NS_EXIF), ("photoshop", NS_PHOTOSHOP), ("crs", NS_CRS)];
+
+#[derive(Debug, Default, Clone)]
+pub struct XmpData {
+    pub rating: Option<i32>,
+    pub label: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub keywords: Vec<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub place: Option<String>,
+    pub crop: Option<Crop>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Crop {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub angle: f32,
+}
+
+type Properties = HashMap<(String, String), Vec<String>>;
+
+impl XmpData {
+    pub fn from_file_data(data: &[u8]) -> Option<XmpData> {
+        let start = find(data, PACKET_START)?;
+        let end = find(&data[start..], PACKET_END)? + start + PACKET_END.len();
+        let packet = std::str::from_utf8(&data[start..end]).ok()?;
+        Some(XmpData::parse(packet))
+    }
+
+    pub fn from_file(path: &Path) -> Option<XmpData> {
+        let mut reader = BufReader::new(File::open(path).ok()?);
+        let mut window = Vec::new();
+        let mut chunk = vec![0; 64 * 1024];
+        let mut started = false;
+
+        loop {
+            let read = reader.read(&mut chunk).ok()?;
+            if read == 0 {
+                return None;
+            }
+            let searched = window.len().saturating_sub(PACKET_END.len());
+            window.extend_from_slice(&chunk[..read]);
+
+            if !started {
+                match find(&window, PACKET_START) {
+                    Some(start) => {
+                        window.drain(..start);
+                        started = true;
+                    }
+                    None => {
+                        window.drain(..window.len().saturating_sub(PACKET_START.len() - 1));
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(end) = find(&window[searched.min(window.len())..], PACKET_END) {
+                let end = searched.min(window.len()) + end + PACKET_END.len();
+                return XmpData::from_file_data(&window[..end]);
+            }
+            if window.len() > MAX_PACKET_SIZE {
+                return None;
+            }
+        }
+    }
+
+    pub fn from_item(item: &utils::Item, keywords: Vec<String>) -> XmpData {
+        XmpData {
+            rating: Some(if item.flag < 0 { -1 } else { item.rating as i32 }),
+            label: item.color_label.clone(),
+            title: item.title.clone(),
+            description: item.caption.clone(),
+            keywords,
+            latitude: item.latitude,
+            longitude: item.longitude,
+            place: item.place_name.clone(),
+            crop: item.crop,
+        }
+    }
+
+    pub fn parse(packet: &str) -> XmpData {
+        let properties = read_properties(packet);
+        let all = |ns: &str, name: &str| properties.get(&(ns.to_string(), name.to_string())).cloned().unwrap_or_default();
+        let first = |ns: &str, name: &str| all(ns, name).into_iter().map(|value| value.trim().to_string()).find(|value| !value.is_empty());
+
+        let hierarchical: Vec<String> = all(NS_LR, "hierarchicalSubject").iter().map(|keyword| keyword.replace('|', "/")).collect();
+        let keywords = if hierarchical.is_empty() { all(NS_DC, "subject") } else { hierarchical };
+
+        let place = [first(NS_PHOTOSHOP, "City"), first(NS_PHOTOSHOP, "State"), first(NS_PHOTOSHOP, "Country")]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let crop = if first(NS_CRS, "HasCrop").is_some_and(|has_crop| has_crop.eq_ignore_ascii_case("true")) {
+            let value = |name: &str, default: f32| first(NS_CRS, name).and_then(|value| value.parse::<f32>().ok()).unwrap_or(default);
+            Some(Crop {
+                left: value("CropLeft", 0.0),
+                top: value("CropTop", 0.0),
+                right: value("CropRight", 1.0),
+                bottom: value("CropBottom", 1.0),
+                angle: value("CropAngle", 0.0),
+            })
+        } else {
+            None
+        };
+
+        XmpData {
+            rating: first(NS_XMP, "Rating").and_then(|rating| rating.parse::<f32>().ok()).map(|rating| rating.round() as i32),
+            label: first(NS_XMP, "Label"),
+            title: first(NS_DC, "title"),
+            description: first(NS_DC, "description"),
+            keywords: keywords.into_iter().map(|keyword| keyword.trim().to_string()).filter(|keyword| !keyword.is_empty()).collect(),
+            latitude: first(NS_EXIF, "GPSLatitude").and_then(|value| parse_coordinate(&value)),
+            longitude: first(NS_EXIF, "GPSLongitude").and_then(|value| parse_coordinate(&value)),
+            place: if place.is_empty() { None } else { Some(place) },
+            crop,
+        }
+    }
+
+    pub fn or(self, fallback: XmpData) -> XmpData {
+        XmpData {
+            rating: self.rating.or(fallback.rating),
+            label: self.label.or(fallback.label),
+            title: self.title.or(fallback.title),
+            description: self.description.or(fallback.description),
+            keywords: if self.keywords.is_empty() { fallback.keywords } else { self.keywords },
+            latitude: self.latitude.or(fallback.latitude),
+            longitude: self.longitude.or(fallback.longitude),
+            place: self.place.or(fallback.place),
+            crop: self.crop.or(fallback.crop),
+        }
+    }
+
+    pub fn to_description(&self) -> String {
+        let mut description = String::from("  <rdf:Description rdf:about=\"\"");
+        for (prefix, namespace) in NAMESPACES {
+            description.push_str(&format!("\n    xmlns:{}=\"{}\"", prefix, namespace));
+        }
+        for (name, value) in self.attributes() {
+            description.push_str(&format!("\n    {}=\"{}\"", name, escape(value.as_str())));
+        }
+
+        let elements = self.elements();
+        if elements.is_empty() {
+            description.push_str("/>\n");
+        } else {
+            description.push_str(">\n");
+            for (_, element) in &elements {
+                description.push_str(element);
+            }
+            description.push_str("  </rdf:Description>\n");
+        }
+
+        description
+    }
+
+    fn attributes(&self) -> Vec<(&'static str, String)> {
+        let mut attributes = Vec::new();
+
+        if let Some(rating) = self.rating {
+            attributes.push(("xmp:Rating", rating.to_string()));
+        }
+        if let Some(label) = &self.label {
+            attributes.push(("xmp:Label", label.clone()));
+        }
+        if let (Some(latitude), Some(longitude)) = (self.latitude, self.longitude) {
+            attributes.push(("exif:GPSLatitude", format_coordinate(latitude, 'N', 'S')));
+            attributes.push(("exif:GPSLongitude", format_coordinate(longitude, 'E', 'W')));
+        }
+        if let Some(place) = &self.place {
+            let parts: Vec<&str> = place.split(", ").collect();
+            let (city, region) = parts.split_at(parts.len().saturating_sub(2));
+            let (city, state, country) = match region {
+                [state, country] if !city.is_empty() => (city.join(", "), Some(*state), Some(*country)),
+                [city, country] => (city.to_string(), None, Some(*country)),
+                _ => (place.clone(), None, None),
+            };
+            attributes.push(("photoshop:City", city));
+            attributes.extend(state.map(|state| ("photoshop:State", state.to_string())));
+            attributes.extend(country.map(|country| ("photoshop:Country", country.to_string())));
+        }
+        if let Some(crop) = &self.crop {
+            attributes.push(("crs:HasCrop", "True".to_string()));
+            attributes.push(("crs:CropLeft", crop.left.to_string()));
+            attributes.push(("crs:CropTop", crop.top.to_string()));
+            attributes.push(("crs:CropRight", crop.right.to_string()));
+            attributes.push(("crs:CropBottom", crop.bottom.to_string()));
+            attributes.push(("crs:CropAngle", crop.angle.to_string()));
+        }
+
+        attributes
+    }
+
+    fn elements(&self) -> Vec<(&'static str, String)> {
+        let mut elements = Vec::new();
+
+        if let Some(title) = &self.title {
+            elements.push(("dc:title", alt_element("dc:title", title)));
+        }
+        if let Some(description) = &self.description {
+            elements.push(("dc:description", alt_element("dc:description", description)));
+        }
+        if !self.keywords.is_empty() {
+            let leaves: Vec<&str> = self.keywords.iter().map(|keyword| keyword.rsplit('/').next().unwrap_or(keyword)).collect();
+            let hierarchical: Vec<String> = self.keywords.iter().map(|keyword| keyword.replace('/', "|")).collect();
+            elements.push(("dc:subject", bag_element("dc:subject", &leaves)));
+            elements.push(("lr:hierarchicalSubject", bag_element("lr:hierarchicalSubject", &hierarchical)));
+        }
+
+        elements
+    }
+
+    pub fn to_packet(&self) -> String {
+        let mut packet = String::from("<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n");
+        packet.push_str("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n");
+        packet.push_str(&format!(" <rdf:RDF xmlns:rdf=\"{}\">\n", NS_RDF));
+        packet.push_str(&self.to_description());
+        packet.push_str(" </rdf:RDF>\n</x:xmpmeta>\n<?xpacket end=\"w\"?>");
+        packet
+    }
+
+    pub fn merge_into(&self, packet: &str) -> String {
+        match self.replace_properties(packet) {
+            Ok(Some(merged)) => merged,
+            Ok(None) => match packet.rfind("</rdf:RDF>") {
+                Some(position) => format!("{}{}{}", &packet[..position], self.to_description(), &packet[position..]),
+                None => self.to_packet(),
+            },
+            Err(e) => {
+                log::warn!("Unable to merge XMP metadata: {}", e);
+                self.to_packet()
+            }
+        }
+    }
+
+    fn replace_properties(&self, packet: &str) -> Result<Option<String>, quick_xml::Error> {
+        let attributes = self.attributes();
+        let elements = self.elements();
+        let place = if self.place.is_some() { PLACE_PROPERTIES.as_slice() } else { &[] };
+        let managed: Vec<(String, String)> =
+            attributes.iter().map(|(name, _)| *name).chain(elements.iter().map(|(name, _)| *name)).chain(place.iter().copied()).map(qualified_name).collect();
+
+        let mut reader = NsReader::from_str(packet);
+        let mut writer = Writer::new(Vec::new());
+        let mut descriptions: Vec<usize> = Vec::new();
+        let mut target: Option<usize> = None;
+        let mut merged = false;
+        let mut skipping: Option<usize> = None;
+        let mut depth = 0;
+
+        loop {
+            let (namespace, event) = reader.read_resolved_event()?;
+            let namespace = namespace_of(namespace);
+
+            if let Some(skip_depth) = skipping {
+                match event {
+                    Event::Start(_) => depth += 1,
+                    Event::End(_) => {
+                        depth -= 1;
+                        if depth < skip_depth {
+                            skipping = None;
+                        }
+                    }
+                    Event::Eof => break,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match event {
+                Event::Eof => break,
+                Event::Start(element) if descriptions.last() == Some(&depth) && managed.contains(&(namespace.clone(), local_name(&element))) => {
+                    depth += 1;
+                    skipping = Some(depth);
+                }
+                Event::Empty(element) if descriptions.last() == Some(&depth) && managed.contains(&(namespace.clone(), local_name(&element))) => {}
+                Event::Start(element) if namespace == NS_RDF && local_name(&element) == "Description" => {
+                    let start = description_start(&reader, &element, &managed, (!merged).then_some(attributes.as_slice()));
+                    depth += 1;
+                    descriptions.push(depth);
+                    if !merged {
+                        target = Some(depth);
+                        merged = true;
+                    }
+                    writer.write_event(Event::Start(start))?;
+                }
+                Event::Empty(element) if namespace == NS_RDF && local_name(&element) == "Description" => {
+                    let start = description_start(&reader, &element, &managed, (!merged).then_some(attributes.as_slice()));
+                    if !merged && !elements.is_empty() {
+                        let end = start.to_end().into_owned();
+                        writer.write_event(Event::Start(start))?;
+                        write_elements(&mut writer, &elements);
+                        writer.write_event(Event::End(end))?;
+                    } else {
+                        writer.write_event(Event::Empty(start))?;
+                    }
+                    merged = true;
+                }
+                Event::Start(element) => {
+                    depth += 1;
+                    writer.write_event(Event::Start(element))?;
+                }
+                Event::End(element) => {
+                    if descriptions.last() == Some(&depth) {
+                        descriptions.pop();
+                        if target == Some(depth) {
+                            write_elements(&mut writer, &elements);
+                            target = None;
+                        }
+                    }
+                    depth -= 1;
+                    writer.write_event(Event::End(element))?;
+                }
+                event => writer.write_event(event)?,
+            }
+        }
+
+        if !merged {
+            return Ok(None);
+        }
+
+        Ok(Some(String::from_utf8_lossy(&writer.into_inner()).into_owned()))
+    }
+}
+
+fn description_start<'a>(
+    reader: &NsReader<&[u8]>,
+    element: &'a BytesStart,
+    managed: &[(String, String)],
+    attributes: Option<&[(&str, String)]>,
+) -> BytesStart<'a> {
+    let mut start = BytesStart::new(String::from_utf8_lossy(element.name().as_ref()).into_owned());
+
+    for attribute in element.attributes().flatten() {
+        let (namespace, local) = reader.resolve_attribute(attribute.key);
+        let name = (namespace_of(namespace), String::from_utf8_lossy(local.as_ref()).into_owned());
+        if !attribute.key.as_ref().starts_with(b"xmlns") && managed.contains(&name) {
+            continue;
+        }
+        start.push_attribute(attribute);
+    }
+
+    let Some(attributes) = attributes else {
+        return start;
+    };
+
+    for (prefix, namespace) in NAMESPACES {
+        let declaration = format!("xmlns:{}", prefix);
+        if start.try_get_attribute(declaration.as_str()).ok().flatten().is_none() {
+            start.push_attribute((declaration.as_str(), namespace));
+        }
+    }
+    for (name, value) in attributes {
+        start.push_attribute((*name, value.as_str()));
+    }
+
+    start
+}
+
+fn write_elements(writer: &mut Writer<Vec<u8>>, elements: &[(&str, String)]) {
+    for (_, element) in elements {
+        writer.get_mut().extend_from_slice(element.as_bytes());
+    }
+}
+
+fn qualified_name(name: &str) -> (String, String) {
+    let (prefix, local) = name.split_once(':').unwrap_or(("", name));
+    let namespace = NAMESPACES.iter().find(|(candidate, _)| *candidate == prefix).map(|(_, namespace)| *namespace).unwrap_or_default();
+    (namespace.to_string(), local.to_string())
+}
+
+fn parse_coordinate(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let direction = value.chars().last()?.to_ascii_uppercase();
+    let (numbers, sign) = match direction {
+        'N' | 'E' => (&value[..value.len() - 1], 1.0),
+        'S' | 'W' => (&value[..value.len() - 1], -1.0),
+        _ => (value, 1.0),
+    };
+
+    let mut parts = numbers.split(',').map(|part| part.trim().parse::<f64>());
+    let degrees = parts.next()?.ok()?;
+    let minutes = parts.next().and_then(Result::ok).unwrap_or(0.0);
+    let seconds = parts.next().and_then(Result::ok).unwrap_or(0.0);
+
+    Some(sign * (degrees + minutes / 60.0 + seconds / 3600.0))
+}
+
+fn format_coordinate(value: f64, positive: char, negative: char) -> String {
+    let absolute = value.abs();
+    let degrees = absolute.trunc();
+    let minutes = (absolute - degrees) * 60.0;
+
+    format!("{},{:.6}{}", degrees as u32, minutes, if value < 0.0 { negative } else { positive })
+}
+
+fn bag_element<S: AsRef<str>>(name: &str, values: &[S]) -> String {
+    let mut element = format!("   <{}>\n    <rdf:Bag>\n", name);
+    for value in values {
+        element.push_str(&format!("     <rdf:li>{}</rdf:li>\n", escape(value.as_ref())));
+    }
+    element.push_str(&format!("    </rdf:Bag>\n   </{}>\n", name));
+    element
+}
+
+fn alt_element(name: &str, value: &str) -> String {
+    format!(
+        "   <{0}>\n    <rdf:Alt>\n     <rdf:li xml:lang=\"x-default\">{1}</rdf:li>\n    </rdf:Alt>\n   </{0}>\n",
+        name,
+        escape(value)
+    )
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn read_properties(packet: &str) -> Properties {
+    let mut reader = NsReader::from_str(packet);
+    reader.config_mut().trim_text(true);
+
+    let mut properties = Properties::new();
+    let mut stack: Vec<(String, String)> = Vec::new();
+
+    loop {
+        let (namespace, event) = match reader.read_resolved_event() {
+            Ok((namespace, event)) => (namespace_of(namespace), event),
+            Err(e) => {
+                log::warn!("Unable to read XMP metadata: {}", e);
+                break;
+            }
+        };
+
+        match event {
+            Event::Start(element) => {
+                let name = (namespace, local_name(&element));
+                read_description(&reader, &name, &element, &mut properties);
+                stack.push(name);
+            }
+            Event::Empty(element) => {
+                let name = (namespace, local_name(&element));
+                read_description(&reader, &name, &element, &mut properties);
+            }
+            Event::Text(text) => {
+                let Some(owner) = stack.iter().rev().find(|(ns, _)| ns != NS_RDF && ns != "adobe:ns:meta/") else {
+                    continue;
+                };
+                if let Ok(value) = text.unescape() {
+                    properties.entry(owner.clone()).or_default().push(value.to_string());
+                }
+            }
+            Event::End(_) => {
+                stack.pop();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    properties
+}
+
+fn read_description(reader: &NsReader<&[u8]>, name: &(String, String), element: &BytesStart, properties: &mut Properties) {
+    if name.0 != NS_RDF || name.1 != "Description" {
+        return;
+    }
+
+    for attribute in element.attributes().flatten() {
+        if attribute.key.as_ref().starts_with(b"xmlns") {
+            continue;
+        }
+
+        let (namespace, local) = reader.resolve_attribute(attribute.key);
+        let namespace = namespace_of(namespace);
+        if namespace == NS_RDF {
+            continue;
+        }
+
+        if let Ok(value) = attribute.unescape_value() {
+            let key = (namespace, String::from_utf8_lossy(local.as_ref()).to_string());
+            properties.entry(key).or_default().push(value.to_string());
+        }
+    }
+}
+
+fn namespace_of(result: ResolveResult) -> String {
+    match result {
+        ResolveResult::Bound(namespace) => String::from_utf8_lossy(namespace.as_ref()).to_string(),
+        _ => String::new(),
+    }
+}
+
+fn local_name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.local_name().as_ref()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets_round_trip() {
+        let xmp = XmpData {
+            rating: Some(4),
+            label: Some("Red & Blue".to_string()),
+            title: Some("Harbour".to_string()),
+            keywords: vec!["Places/Lisbon".to_string(), "Boats".to_string()],
+            latitude: Some(38.7),
+            longitude: Some(-9.14),
+            ..Default::default()
+        };
+        let parsed = XmpData::parse(&xmp.to_packet());
+
+        assert_eq!(parsed.rating, Some(4));
+        assert_eq!(parsed.label.as_deref(), Some("Red & Blue"));
+        assert_eq!(parsed.title.as_deref(), Some("Harbour"));
+        assert_eq!(parsed.keywords, vec!["Places/Lisbon", "Boats"]);
+        assert!((parsed.latitude.unwrap() - 38.7).abs() < 1e-6);
+        assert!((parsed.longitude.unwrap() + 9.14).abs() < 1e-6);
+    }
+
+    #[test]
+    fn places_are_written_back() {
+        for place in ["Porto", "Porto, Portugal", "Lisbon, Lisboa, Portugal"] {
+            let xmp = XmpData { place: Some(place.to_string()), ..Default::default() };
+            assert_eq!(XmpData::parse(&xmp.to_packet()).place.as_deref(), Some(place));
+        }
+
+        let existing = XmpData { place: Some("Lisbon, Lisboa, Portugal".to_string()), ..Default::default() }.to_packet();
+        let merged = XmpData { place: Some("Porto, Portugal".to_string()), ..Default::default() }.merge_into(&existing);
+        assert_eq!(XmpData::parse(&merged).place.as_deref(), Some("Porto, Portugal"));
+    }
+
+    #[test]
+    fn merging_replaces_existing_properties() {
+        let existing = XmpData { rating: Some(2), title: Some("Old".to_string()), ..Default::default() }
+            .to_packet()
+            .replace("xmp:Rating=\"2\"", "xmp:Rating=\"2\"\n    photoshop:City=\"Porto\" xmlns:photoshop=\"http://ns.adobe.com/photoshop/1.0/\"");
+        let merged = XmpData { rating: Some(5), title: Some("New".to_string()), ..Default::default() }.merge_into(&existing);
+        let parsed = XmpData::parse(&merged);
+
+        assert_eq!(merged.matches("<rdf:Description").count(), 1);
+        assert_eq!(merged.matches("<dc:title>").count(), 1);
+        assert_eq!(parsed.rating, Some(5));
+        assert_eq!(parsed.title.as_deref(), Some("New"));
+        assert_eq!(parsed.place.as_deref(), Some("Porto"));
+    }
+
+    #[test]
+    fn merging_adds_properties_to_empty_descriptions() {
+        let existing = "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\"><rdf:Description rdf:about=\"\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" xmp:Rating=\"1\"/></rdf:RDF></x:xmpmeta>";
+        let merged = XmpData { rating: Some(3), keywords: vec!["Boats".to_string()], ..Default::default() }.merge_into(existing);
+        let parsed = XmpData::parse(&merged);
+
+        assert_eq!(merged.matches("<rdf:Description").count(), 1);
+        assert_eq!(parsed.rating, Some(3));
+        assert_eq!(parsed.keywords, vec!["Boats"]);
+    }
+    #[test]
+    fn packets_are_found_across_read_chunks() {
+        let path = std::env::temp_dir().join(format!("xmp-{}.jpg", std::process::id()));
+        let packet = XmpData { title: Some("Harbour".to_string()), ..Default::default() }.to_packet();
+        let mut data = vec![0; 64 * 1024 - 20];
+        data.extend_from_slice(packet.as_bytes());
+        data.extend_from_slice(&[0; 100]);
+        std::fs::write(&path, &data).unwrap();
+
+        assert_eq!(XmpData::from_file(&path).unwrap().title.as_deref(), Some("Harbour"));
+
+        std::fs::write(&path, &data[..64 * 1024 - 20 + packet.find("</x:xmpmeta>").unwrap()]).unwrap();
+        assert!(XmpData::from_file(&path).is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
\ No newline at end of file
@@ -1,6 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { tryCatch } from "./utils";
-import type { Library, Item } from "./models";
+import type { Library, Item, ItemQuery, Album, SmartRule, Tag, ColorLabel, ItemFlag, EditOperation, ExportOptions, GalleryOptions, ExportProgress, Operation, IntegrityIssue, IntegrityReport, RepairReport, VerifyProgress, RehashProgress, ImportReport, ConsolidateReport } from "./models";
 
 export function getLibraries() {
     return tryCatch(() => invoke<Library[]>("get_libraries"));
@@ -18,6 +19,10 @@ export function updateLibraryPath(libraryId: string, newPath: string) {
     return tryCatch(() => invoke("update_library_path", { libraryId, newPath }));
 }
 
+export function setLibraryWriteSidecars(libraryId: string, enabled: boolean) {
+    return tryCatch(() => invoke("set_library_write_sidecars", { libraryId, enabled }));
+}
+
 export function removeLibrary(libraryId: string) {
     return tryCatch(() => invoke("remove_library", { libraryId }));
 }
@@ -30,14 +35,198 @@ export function setSelectedLibrary(libraryId: string | null) {
     return tryCatch(() => invoke("set_selected_library", { libraryId }));
 }
 
-export function getItems(libraryId: string) {
-    return tryCatch(() => invoke<Item[]>("get_items", { libraryId }));
+export function getItems(libraryId: string, query?: ItemQuery) {
+    return tryCatch(() => invoke<Item[]>("get_items", { libraryId, query }));
 }
 
-export function addItems(libraryId: string, sourcePaths: string[], deleteSource: boolean) {
-    return tryCatch(() => invoke<Item[]>("add_items", { libraryId, sourcePaths, deleteSource }));
+export function addItems(libraryId: string, sourcePaths: string[], deleteSource: boolean, reference?: boolean) {
+    return tryCatch(() => invoke<ImportReport>("add_items", { libraryId, sourcePaths, deleteSource, reference }));
 }
 
 export function setItemsFavorite(libraryId: string, itemIds: string[], value: boolean) {
     return tryCatch(() => invoke("set_items_favorite", { libraryId, itemIds, value }));
+}
+
+export function setItemsRating(libraryId: string, itemIds: string[], value: number) {
+    return tryCatch(() => invoke("set_items_rating", { libraryId, itemIds, value }));
+}
+
+export function setItemsColorLabel(libraryId: string, itemIds: string[], value: ColorLabel | null) {
+    return tryCatch(() => invoke("set_items_color_label", { libraryId, itemIds, value }));
+}
+
+export function setItemsFlag(libraryId: string, itemIds: string[], value: ItemFlag) {
+    return tryCatch(() => invoke("set_items_flag", { libraryId, itemIds, value }));
+}
+
+export function setItemDetails(libraryId: string, itemId: string, title: string | null, caption: string | null, notes: string | null) {
+    return tryCatch(() => invoke("set_item_details", { libraryId, itemId, title, caption, notes }));
+}
+
+export function setItemsTitle(libraryId: string, itemIds: string[], value: string | null) {
+    return tryCatch(() => invoke("set_items_title", { libraryId, itemIds, value }));
+}
+
+export function setItemsCaption(libraryId: string, itemIds: string[], value: string | null) {
+    return tryCatch(() => invoke("set_items_caption", { libraryId, itemIds, value }));
+}
+
+export function setItemsNotes(libraryId: string, itemIds: string[], value: string | null) {
+    return tryCatch(() => invoke("set_items_notes", { libraryId, itemIds, value }));
+}
+
+export function getItemXmp(libraryId: string, itemId: string) {
+    return tryCatch(() => invoke<string>("get_item_xmp", { libraryId, itemId }));
+}
+
+export function deleteItems(libraryId: string, itemIds: string[]) {
+    return tryCatch(() => invoke("delete_items", { libraryId, itemIds }));
+}
+
+export function setItemsDate(libraryId: string, itemIds: string[], value: string) {
+    return tryCatch(() => invoke("set_items_date", { libraryId, itemIds, value }));
+}
+
+export function shiftItemsDate(libraryId: string, itemIds: string[], seconds: number) {
+    return tryCatch(() => invoke("shift_items_date", { libraryId, itemIds, seconds }));
+}
+
+export function setItemsTimeZone(libraryId: string, itemIds: string[], offsetMinutes: number, keepLocalTime: boolean) {
+    return tryCatch(() => invoke("set_items_time_zone", { libraryId, itemIds, offsetMinutes, keepLocalTime }));
+}
+
+export function revertItemsDate(libraryId: string, itemIds: string[]) {
+    return tryCatch(() => invoke("revert_items_date", { libraryId, itemIds }));
+}
+
+export function getItemEdits(libraryId: string, itemId: string) {
+    return tryCatch(() => invoke<EditOperation[]>("get_item_edits", { libraryId, itemId }));
+}
+
+export function setItemEdits(libraryId: string, itemId: string, edits: EditOperation[]) {
+    return tryCatch(() => invoke("set_item_edits", { libraryId, itemId, edits }));
+}
+
+export function revertItemEdits(libraryId: string, itemIds: string[]) {
+    return tryCatch(() => invoke("revert_item_edits", { libraryId, itemIds }));
+}
+
+export function rotateItems(libraryId: string, itemIds: string[], degrees: 90 | 180 | 270, lossless: boolean) {
+    return tryCatch(() => invoke("rotate_items", { libraryId, itemIds, degrees, lossless }));
+}
+
+export function exportItems(libraryId: string, itemIds: string[], destination: string, options?: ExportOptions) {
+    return tryCatch(() => invoke<string[]>("export_items", { libraryId, itemIds, destination, options }));
+}
+
+export function exportAlbumGallery(libraryId: string, albumId: string, destination: string, options?: GalleryOptions) {
+    return tryCatch(() => invoke<string>("export_album_gallery", { libraryId, albumId, destination, options }));
+}
+
+export function exportAlbumBundle(libraryId: string, albumId: string, destination: string) {
+    return tryCatch(() => invoke<string>("export_album_bundle", { libraryId, albumId, destination }));
+}
+
+export function importAlbumBundle(libraryId: string, bundlePath: string) {
+    return tryCatch(() => invoke<Album[]>("import_album_bundle", { libraryId, bundlePath }));
+}
+
+export function onExportProgress(callback: (progress: ExportProgress) => void) {
+    return listen<ExportProgress>("export-progress", event => callback(event.payload));
+}
+
+export function search(libraryId: string, text: string, query?: ItemQuery) {
+    return tryCatch(() => invoke<Item[]>("search", { libraryId, text, query }));
+}
+
+export function createSmartAlbum(libraryId: string, name: string, description: string | null, rule: SmartRule) {
+    return tryCatch(() => invoke<Album>("create_smart_album", { libraryId, name, description, rule }));
+}
+
+export function updateSmartAlbumRule(libraryId: string, albumId: string, rule: SmartRule) {
+    return tryCatch(() => invoke("update_smart_album_rule", { libraryId, albumId, rule }));
+}
+
+export function getSmartAlbumItems(libraryId: string, albumId: string) {
+    return tryCatch(() => invoke<Item[]>("get_smart_album_items", { libraryId, albumId }));
+}
+
+export function addItemsToAlbum(libraryId: string, albumId: string, itemIds: string[]) {
+    return tryCatch(() => invoke("add_items_to_album", { libraryId, albumId, itemIds }));
+}
+
+export function removeItemsFromAlbum(libraryId: string, albumId: string, itemIds: string[]) {
+    return tryCatch(() => invoke("remove_items_from_album", { libraryId, albumId, itemIds }));
+}
+
+export function getTags(libraryId: string) {
+    return tryCatch(() => invoke<Tag[]>("get_tags", { libraryId }));
+}
+
+export function createTag(libraryId: string, path: string) {
+    return tryCatch(() => invoke<string>("create_tag", { libraryId, path }));
+}
+
+export function renameTag(libraryId: string, tagId: string, name: string) {
+    return tryCatch(() => invoke("rename_tag", { libraryId, tagId, name }));
+}
+
+export function mergeTags(libraryId: string, sourceIds: string[], targetId: string) {
+    return tryCatch(() => invoke("merge_tags", { libraryId, sourceIds, targetId }));
+}
+
+export function deleteTag(libraryId: string, tagId: string) {
+    return tryCatch(() => invoke("delete_tag", { libraryId, tagId }));
+}
+
+export function tagItems(libraryId: string, itemIds: string[], tagId: string) {
+    return tryCatch(() => invoke("tag_items", { libraryId, itemIds, tagId }));
+}
+
+export function untagItems(libraryId: string, itemIds: string[], tagId: string) {
+    return tryCatch(() => invoke("untag_items", { libraryId, itemIds, tagId }));
+}
+
+export function getHistory(libraryId: string, limit?: number) {
+    return tryCatch(() => invoke<Operation[]>("get_history", { libraryId, limit }));
+}
+
+export function undo(libraryId: string) {
+    return tryCatch(() => invoke<Operation | null>("undo", { libraryId }));
+}
+
+export function redo(libraryId: string) {
+    return tryCatch(() => invoke<Operation | null>("redo", { libraryId }));
+}
+
+export function verifyLibrary(libraryId: string) {
+    return tryCatch(() => invoke<IntegrityReport>("verify_library", { libraryId }));
+}
+
+export function repairLibrary(libraryId: string) {
+    return tryCatch(() => invoke<RepairReport>("repair_library", { libraryId }));
+}
+
+export function onVerifyProgress(callback: (progress: VerifyProgress) => void) {
+    return listen<VerifyProgress>("verify-progress", event => callback(event.payload));
+}
+
+export function onVerifyIssue(callback: (issue: IntegrityIssue) => void) {
+    return listen<IntegrityIssue>("verify-issue", event => callback(event.payload));
+}
+
+export function rehashLibrary(libraryId: string) {
+    return tryCatch(() => invoke("rehash_library", { libraryId }));
+}
+
+export function onRehashProgress(callback: (progress: RehashProgress) => void) {
+    return listen<RehashProgress>("rehash-progress", event => callback(event.payload));
+}
+
+export function refreshItemStatus(libraryId: string) {
+    return tryCatch(() => invoke<string[]>("refresh_item_status", { libraryId }));
+}
+
+export function consolidateItems(libraryId: string, itemIds: string[]) {
+    return tryCatch(() => invoke<ConsolidateReport>("consolidate_items", { libraryId, itemIds }));
 }
\ No newline at end of file
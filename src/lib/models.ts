@@ -4,6 +4,7 @@ export interface Library {
     icon: string;
     color: string;
     path: string;
+    write_sidecars?: boolean;
 }
 
 export interface Item {
@@ -14,11 +15,188 @@ export interface Item {
     width: number;
     height: number;
     checksum: string;
+    checksum_algorithm: ChecksumAlgorithm;
     is_favorite: boolean;
     is_screenshot: boolean;
     is_screen_recording: boolean;
     live_video?: string;
     created_at: string;
+    page_count: number;
+    is_animated: boolean;
+    frame_count: number;
+    duration?: number;
+    color_space?: string;
+    is_hdr: boolean;
+    has_gain_map: boolean;
+    bit_depth: number;
+    camera_model?: string;
+    place_name?: string;
+    rating: number;
+    color_label?: ColorLabel;
+    flag: ItemFlag;
+    title?: string;
+    caption?: string;
+    notes?: string;
+    latitude?: number;
+    longitude?: number;
+    crop?: Crop;
+    time_zone?: number;
+    original_created_at?: string;
+    original_time_zone?: number;
+    edits: EditOperation[];
+    source_path?: string;
+    is_offline: boolean;
+}
+
+export interface Crop {
+    left: number;
+    top: number;
+    right: number;
+    bottom: number;
+    angle: number;
+}
+
+export interface ExportOptions {
+    original?: boolean;
+    embed_metadata?: boolean;
+    name_template?: string;
+    max_size?: number;
+    format?: ExportFormat;
+    quality?: number;
+    strip?: MetadataStrip;
+}
+
+export type ExportFormat = "jpeg" | "webp" | "png";
+
+export type MetadataStrip = "none" | "location" | "all";
+
+export interface GalleryOptions {
+    image_size?: number;
+    thumbnail_size?: number;
+    quality?: number;
+}
+
+export interface ExportProgress {
+    item_id: string;
+    completed: number;
+    total: number;
+}
+
+export type AspectRatio = "original" | "1:1" | "5:4" | "4:3" | "3:2" | "16:9";
+
+export type EditOperation =
+    | { type: "rotate"; degrees: 0 | 90 | 180 | 270 }
+    | { type: "flip"; horizontal: boolean }
+    | { type: "crop"; left: number; top: number; right: number; bottom: number; aspect?: AspectRatio }
+    | { type: "straighten"; angle: number }
+    | { type: "exposure"; value: number }
+    | { type: "contrast"; value: number }
+    | { type: "saturation"; value: number }
+    | { type: "white_balance"; temperature: number; tint: number };
+
+export type ColorLabel = "Red" | "Yellow" | "Green" | "Blue" | "Purple";
+
+export type ItemFlag = -1 | 0 | 1;
+
+export interface ItemQuery {
+    is_favorite?: boolean;
+    is_animated?: boolean;
+    is_hdr?: boolean;
+    tag_id?: string;
+    min_rating?: number;
+    color_label?: ColorLabel;
+    flag?: ItemFlag;
+}
+
+export interface Album {
+    id: string;
+    name: string;
+    description?: string;
+    parent?: string;
+    color?: string;
+    emoji?: string;
+    created_at: string;
+    smart_rule?: SmartRule;
+}
+
+export interface SmartRule {
+    match_all: boolean;
+    conditions: RuleCondition[];
+}
+
+export type RuleCondition =
+    | { type: "date_range"; from?: string; to?: string }
+    | { type: "favorite"; value: boolean }
+    | { type: "file_type"; value: string }
+    | { type: "camera"; value: string }
+    | { type: "dimensions"; min_width?: number; max_width?: number; min_height?: number; max_height?: number }
+    | { type: "album"; album_id: string; member: boolean }
+    | { type: "keyword"; value: string }
+    | { type: "color"; value: ColorLabel };
+
+export interface Tag {
+    id: string;
+    name: string;
+    parent?: string;
+    path: string;
+    item_count: number;
+}
+
+export interface Operation {
+    id: number;
+    name: string;
+    created_at: string;
+    undone: boolean;
+    change_count: number;
+}
+
+export type IntegrityIssue =
+    | { kind: "missing_original"; item_id: string }
+    | { kind: "offline"; item_id: string }
+    | { kind: "checksum_mismatch"; item_id: string }
+    | { kind: "missing_thumbnail"; item_id: string }
+    | { kind: "orphan_file"; file: string };
+
+export interface IntegrityReport {
+    checked: number;
+    issues: IntegrityIssue[];
+}
+
+export interface RepairReport {
+    reimported: Item[];
+    thumbnails: string[];
+    quarantined: string[];
+}
+
+export interface VerifyProgress {
+    item_id: string;
+    completed: number;
+    total: number;
+}
+
+export interface ImportReport {
+    items: Item[];
+    unremoved_sources: UnremovedSource[];
+}
+
+export interface ConsolidateReport {
+    consolidated: string[];
+    offline: string[];
+    mismatched: string[];
+}
+
+export interface UnremovedSource {
+    path: string;
+    reason: string;
+}
+
+export type ChecksumAlgorithm = "md5" | "blake3";
+
+export interface RehashProgress {
+    item_id: string;
+    completed: number;
+    total: number;
+    mismatch: boolean;
 }
 
 export interface Notification {
@@ -276,6 +276,7 @@ interface ImportOptions {
     livePhotos: boolean;
     deleteImported: boolean;
     ignoreImported: boolean;
+    referenceInPlace: boolean;
 }
 
 function ImportDialog({ openDialog, onOpenChange }: { openDialog: boolean; onOpenChange: (open: boolean) => void }) {
@@ -289,7 +290,11 @@ function ImportDialog({ openDialog, onOpenChange }: { openDialog: boolean; onOpe
 
         onOpenChange(false);
         pushNoti("Importing items", "Importing " + selectedItems.length + " items", "promise", {
-            promise: addItems(selectedLibrary.id, selectedItems, opts.deleteImported),
+            promise: addItems(selectedLibrary.id, selectedItems, opts.deleteImported, opts.referenceInPlace).then(result => {
+                const unremoved = result.data?.unremoved_sources.length ?? 0;
+                if (unremoved > 0) pushNoti("Some files were not removed", unremoved + " imported files could not be removed from their original location", "warning");
+                return result;
+            }),
             peek: "Importing " + selectedItems.length + " items",
             success: { title: "Import success", description: selectedItems.length + " items added successfully" },
             error: { title: "Error importing", description: "An error occurred while importing the selected items" },
@@ -305,7 +310,7 @@ function ImportDialog({ openDialog, onOpenChange }: { openDialog: boolean; onOpe
                     node: <SourcePage onItemsSelected={setSelectedItems} />,
                 },
                 review: {
-                    height: 418,
+                    height: 450,
                     node: <ReviewPage selectedItems={selectedItems} onImport={importItems} />,
                 },
             }}
@@ -397,6 +402,7 @@ function ReviewPage({ selectedItems, onImport }: { selectedItems: string[]; onIm
     const [livePhotos, setLivePhotos] = useState(false);
     const [deleteImported, setDeleteImported] = useState(false);
     const [ignoreImported, setIgnoreImported] = useState(false);
+    const [referenceInPlace, setReferenceInPlace] = useState(false);
     const { setPage } = useDialogPaged();
 
     return (
@@ -431,18 +437,32 @@ function ReviewPage({ selectedItems, onImport }: { selectedItems: string[]; onIm
                         </FieldLabel>
                     </Field>
                     <Field orientation="horizontal">
-                        <Checkbox id="optionDeleteImport" checked={deleteImported} onCheckedChange={e => setDeleteImported(!!e)} />
+                        <Checkbox id="optionDeleteImport" checked={deleteImported} disabled={referenceInPlace} onCheckedChange={e => setDeleteImported(!!e)} />
                         <FieldLabel htmlFor="optionDeleteImport">Delete originals after import</FieldLabel>
                     </Field>
                     <Field orientation="horizontal">
                         <Checkbox id="optionIgnoreImported" checked={ignoreImported} onCheckedChange={e => setIgnoreImported(!!e)} />
                         <FieldLabel htmlFor="optionIgnoreImported">Ignore already imported items</FieldLabel>
                     </Field>
+                    <Field orientation="horizontal">
+                        <Checkbox id="optionReferenceInPlace" checked={referenceInPlace} onCheckedChange={e => { setReferenceInPlace(!!e); if (e) setDeleteImported(false); }} />
+                        <FieldLabel htmlFor="optionReferenceInPlace">
+                            Reference files in place
+                            <Tooltip>
+                                <TooltipTrigger asChild>
+                                    <QuestionCircle12Regular className="size-4.5 text-primary" />
+                                </TooltipTrigger>
+                                <TooltipContent>
+                                    <p>Items stay in their current location instead of being<br />copied into the library. Only thumbnails are stored locally</p>
+                                </TooltipContent>
+                            </Tooltip>
+                        </FieldLabel>
+                    </Field>
                 </FieldGroup>
             </FieldSet>
             <DialogFooter>
                 <Button variant="outline" onClick={() => setPage("source", true)}>Back</Button>
-                <Button disabled={!selectedItems.length} onClick={() => onImport({ livePhotos, deleteImported, ignoreImported })}>Import items</Button>
+                <Button disabled={!selectedItems.length} onClick={() => onImport({ livePhotos, deleteImported, ignoreImported, referenceInPlace })}>Import items</Button>
             </DialogFooter>
         </>
     );